@@ -0,0 +1,33 @@
+use std::{env, sync::LazyLock};
+
+use reqwest::Client;
+
+// 告警 webhook 地址，未配置时只输出日志
+static ALERT_WEBHOOK_URL: LazyLock<Option<String>> = LazyLock::new(|| {
+    env::var("ALERT_WEBHOOK_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+});
+
+// 发送需要人工介入的告警
+pub async fn send_alert(title: &str, detail: &str) {
+    eprintln!("[ALERT] {}: {}", title, detail);
+
+    let url = match ALERT_WEBHOOK_URL.as_ref() {
+        Some(url) => url,
+        None => return,
+    };
+    let body = serde_json::json!({
+        "title": title,
+        "detail": detail,
+    });
+    let result = Client::new()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await;
+    if let Err(e) = result {
+        eprintln!("Send alert error: {:?}", e);
+    }
+}
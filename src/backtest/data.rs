@@ -0,0 +1,589 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::market::{kline_ticks, Kline, MarketTick};
+use crate::{
+    error::{Error, Result},
+    static_items::symbol::get_symbols,
+};
+
+// 回测数据目录，原始 CSV 放在 raw 子目录，导入后按交易对和数据类型保存为 <symbol>-<kind>.csv，
+// 缺失区间保存为 <symbol>-<kind>-gaps.csv
+static DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    env::var("BACKTEST_DATA_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| "data".to_string())
+        .into()
+});
+
+// 逐笔成交和 bookTicker 相邻数据超过该间隔视为缺失
+pub const TICK_GAP_MS: i64 = 60_000;
+// 新版现货数据使用微秒时间戳，超过该值时按微秒处理
+const MICROS_THRESHOLD: i64 = 100_000_000_000_000;
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataKind {
+    Kline,
+    BookTicker,
+    AggTrade,
+}
+
+impl DataKind {
+    // 未指定数据类型时按该顺序使用已导入的数据
+    pub const PREFERRED: [DataKind; 3] =
+        [DataKind::BookTicker, DataKind::AggTrade, DataKind::Kline];
+
+    fn name(self) -> &'static str {
+        match self {
+            DataKind::Kline => "kline",
+            DataKind::BookTicker => "book_ticker",
+            DataKind::AggTrade => "agg_trade",
+        }
+    }
+}
+
+// 缺失区间，start 和 end 为缺口前后两个数据点的时间
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct Gap {
+    pub start: i64,
+    pub end: i64,
+}
+
+// 从数据文件读取并标准化后的行情
+#[derive(Debug, Clone)]
+pub struct MarketSeries {
+    pub symbol: String,
+    pub kind: DataKind,
+    pub ticks: Vec<MarketTick>,
+    pub gaps: Vec<Gap>,
+    pub max_interval: i64, // 相邻数据超过该间隔视为缺失
+}
+
+fn data_error(message: impl Into<String>, line: usize, column: usize) -> Error {
+    Error::CustomError {
+        message: message.into(),
+        line: line as u32,
+        column: column as u32,
+    }
+}
+
+// 解析 Binance 数据文件名，如 BTCUSDT-1m-2024-01-01.csv、BTCUSDT-bookTicker-2024-01.csv、
+// BTCUSDT-aggTrades-2024-01-01.csv，返回小写交易对、数据类型和 K 线周期（毫秒）
+pub fn parse_file_name(name: &str) -> Result<(String, DataKind, Option<i64>)> {
+    let invalid = || Error::ErrorMessage(format!("Unrecognized data file name: {}", name));
+    let mut parts = name.trim_end_matches(".csv").split('-');
+    let symbol = parts.next().ok_or_else(invalid)?.to_lowercase();
+    if !get_symbols().contains(&symbol) {
+        return Err(Error::ErrorMessage(format!("Unknown symbol: {}", symbol)));
+    }
+    let (kind, interval) = match parts.next().ok_or_else(invalid)? {
+        "bookTicker" => (DataKind::BookTicker, None),
+        "aggTrades" => (DataKind::AggTrade, None),
+        interval => (
+            DataKind::Kline,
+            Some(parse_interval(interval).ok_or_else(invalid)?),
+        ),
+    };
+    Ok((symbol, kind, interval))
+}
+
+// K 线周期转换为毫秒，月线长度不固定，不支持
+fn parse_interval(interval: &str) -> Option<i64> {
+    let split = interval.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = interval.split_at(split);
+    let unit_ms = match unit {
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => return None,
+    };
+    Some(count.parse::<i64>().ok()? * unit_ms)
+}
+
+// CSV 的一行，line 从 1 开始
+struct Row<'a> {
+    line: usize,
+    fields: Vec<&'a str>,
+}
+
+impl Row<'_> {
+    fn field(&self, index: usize) -> Result<&str> {
+        self.fields
+            .get(index)
+            .map(|f| f.trim())
+            .ok_or_else(|| data_error("missing field", self.line, index + 1))
+    }
+
+    fn time(&self, index: usize) -> Result<i64> {
+        let time: i64 = self
+            .field(index)?
+            .parse()
+            .map_err(|_| data_error("invalid timestamp", self.line, index + 1))?;
+        Ok(if time >= MICROS_THRESHOLD {
+            time / 1000
+        } else {
+            time
+        })
+    }
+
+    fn price(&self, index: usize) -> Result<Decimal> {
+        match self.field(index)?.parse::<Decimal>() {
+            Ok(price) if price > Decimal::ZERO => Ok(price),
+            _ => Err(data_error("invalid price", self.line, index + 1)),
+        }
+    }
+}
+
+// 按行拆分 CSV，跳过空行和表头（首行第一个字段不是数字）
+fn rows(content: &str) -> impl Iterator<Item = Row<'_>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter(|(i, line)| {
+            *i > 0
+                || line
+                    .split(',')
+                    .next()
+                    .is_some_and(|f| f.trim().parse::<i64>().is_ok())
+        })
+        .map(|(i, line)| Row {
+            line: i + 1,
+            fields: line.split(',').collect(),
+        })
+}
+
+// 时间必须不递减
+fn check_order(prev: &mut i64, time: i64, line: usize) -> Result<()> {
+    if time < *prev {
+        return Err(data_error("timestamp goes backwards", line, 1));
+    }
+    *prev = time;
+    Ok(())
+}
+
+// open_time,open,high,low,close,volume,close_time,...
+pub fn parse_klines(content: &str) -> Result<Vec<Kline>> {
+    let mut klines = Vec::new();
+    let mut prev = i64::MIN;
+    for row in rows(content) {
+        let kline = Kline {
+            open_time: row.time(0)?,
+            open: row.price(1)?,
+            high: row.price(2)?,
+            low: row.price(3)?,
+            close: row.price(4)?,
+            close_time: row.time(6)?,
+        };
+        if kline.high < kline.open.max(kline.close) || kline.low > kline.open.min(kline.close) {
+            return Err(data_error("high/low out of range", row.line, 3));
+        }
+        if kline.close_time < kline.open_time {
+            return Err(data_error("close time before open time", row.line, 7));
+        }
+        check_order(&mut prev, kline.open_time, row.line)?;
+        klines.push(kline);
+    }
+    Ok(klines)
+}
+
+// update_id,best_bid_price,best_bid_qty,best_ask_price,best_ask_qty,transaction_time,event_time
+pub fn parse_book_tickers(content: &str) -> Result<Vec<MarketTick>> {
+    let mut ticks = Vec::new();
+    let mut prev = i64::MIN;
+    for row in rows(content) {
+        let tick = MarketTick {
+            time: row.time(5)?,
+            bid: row.price(1)?,
+            ask: row.price(3)?,
+        };
+        if tick.bid > tick.ask {
+            return Err(data_error("bid above ask", row.line, 2));
+        }
+        check_order(&mut prev, tick.time, row.line)?;
+        ticks.push(tick);
+    }
+    Ok(ticks)
+}
+
+// agg_trade_id,price,quantity,first_trade_id,last_trade_id,transact_time,is_buyer_maker
+pub fn parse_agg_trades(content: &str) -> Result<Vec<MarketTick>> {
+    let mut ticks = Vec::new();
+    let mut prev = i64::MIN;
+    for row in rows(content) {
+        let price = row.price(1)?;
+        let tick = MarketTick {
+            time: row.time(5)?,
+            bid: price,
+            ask: price,
+        };
+        check_order(&mut prev, tick.time, row.line)?;
+        ticks.push(tick);
+    }
+    Ok(ticks)
+}
+
+// 相邻时间间隔超过 max_interval 的区间
+pub fn find_gaps(times: impl IntoIterator<Item = i64>, max_interval: i64) -> Vec<Gap> {
+    let mut gaps = Vec::new();
+    let mut prev: Option<i64> = None;
+    for time in times {
+        if let Some(prev) = prev {
+            if time - prev > max_interval {
+                gaps.push(Gap {
+                    start: prev,
+                    end: time,
+                });
+            }
+        }
+        prev = Some(time);
+    }
+    gaps
+}
+
+// 读取 Binance 数据文件并标准化为价格序列
+pub fn load_csv(path: &Path) -> Result<MarketSeries> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| Error::ErrorMessage(format!("Invalid path: {:?}", path)))?;
+    let (symbol, kind, interval) = parse_file_name(name)?;
+    let content = fs::read_to_string(path)?;
+    let max_interval = interval.unwrap_or(TICK_GAP_MS);
+    let ticks = match kind {
+        DataKind::Kline => kline_ticks(&parse_klines(&content)?),
+        DataKind::BookTicker => parse_book_tickers(&content)?,
+        DataKind::AggTrade => parse_agg_trades(&content)?,
+    };
+    // K 线按价格点计算，缺口从前一根 K 线的最后一个价格点开始，与合并时的衔接检查一致
+    let gaps = find_gaps(ticks.iter().map(|t| t.time), max_interval);
+    Ok(MarketSeries {
+        symbol,
+        kind,
+        ticks,
+        gaps,
+        max_interval,
+    })
+}
+
+// 原始数据文件的路径，只允许 raw 目录下的文件名
+pub fn raw_file_path(file_name: &str) -> Option<PathBuf> {
+    let valid = !file_name.is_empty()
+        && !file_name.contains(['/', '\\'])
+        && file_name != ".."
+        && file_name.ends_with(".csv");
+    valid.then(|| DATA_DIR.join("raw").join(file_name))
+}
+
+fn series_path(symbol: &str, kind: DataKind) -> PathBuf {
+    DATA_DIR.join(format!("{}-{}.csv", symbol, kind.name()))
+}
+
+fn gaps_path(symbol: &str, kind: DataKind) -> PathBuf {
+    DATA_DIR.join(format!("{}-{}-gaps.csv", symbol, kind.name()))
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_ticks(path: &Path) -> Result<Vec<MarketTick>> {
+    let content = read_file(path)?;
+    let mut ticks = Vec::new();
+    let mut prev = i64::MIN;
+    for row in rows(&content) {
+        let tick = MarketTick {
+            time: row.time(0)?,
+            bid: row.price(1)?,
+            ask: row.price(2)?,
+        };
+        check_order(&mut prev, tick.time, row.line)?;
+        ticks.push(tick);
+    }
+    Ok(ticks)
+}
+
+fn read_gaps(path: &Path) -> Result<Vec<Gap>> {
+    let content = read_file(path)?;
+    rows(&content)
+        .map(|row| {
+            Ok(Gap {
+                start: row.time(0)?,
+                end: row.time(1)?,
+            })
+        })
+        .collect()
+}
+
+// 先写临时文件再替换，避免中断时损坏已有数据
+fn write_file(path: &Path, content: String) -> Result<()> {
+    let tmp = path.with_extension("csv.tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// 合并已保存和新导入的缺失区间：新数据与已有数据衔接处超过 max_interval 时记为缺失，
+// 已被数据填补（区间内有数据点）的缺失区间去掉
+fn merge_gaps(ticks: &[MarketTick], stored: Vec<Gap>, series: &MarketSeries) -> Vec<Gap> {
+    let mut gaps = stored;
+    gaps.extend(series.gaps.iter().cloned());
+    if let (Some(first), Some(last)) = (series.ticks.first(), series.ticks.last()) {
+        let before = ticks.partition_point(|t| t.time < first.time);
+        if let Some(prev) = before.checked_sub(1).map(|i| &ticks[i]) {
+            if first.time - prev.time > series.max_interval {
+                gaps.push(Gap {
+                    start: prev.time,
+                    end: first.time,
+                });
+            }
+        }
+        let after = ticks.partition_point(|t| t.time <= last.time);
+        if let Some(next) = ticks.get(after) {
+            if next.time - last.time > series.max_interval {
+                gaps.push(Gap {
+                    start: last.time,
+                    end: next.time,
+                });
+            }
+        }
+    }
+    gaps.retain(|gap| {
+        let i = ticks.partition_point(|t| t.time <= gap.start);
+        ticks.get(i).is_none_or(|t| t.time >= gap.end)
+    });
+    gaps.sort_by_key(|gap| (gap.start, gap.end));
+    gaps.dedup();
+    gaps
+}
+
+// 合并到该交易对同类型已保存的序列，按时间排序并去掉重复的数据，缺失区间一并保存，
+// 返回合并后的数量
+pub fn save_series(series: &MarketSeries) -> Result<usize> {
+    let path = series_path(&series.symbol, series.kind);
+    let gaps_path = gaps_path(&series.symbol, series.kind);
+    let mut ticks = read_ticks(&path)?;
+    ticks.extend(series.ticks.iter().cloned());
+    ticks.sort_by_key(|t| t.time);
+    ticks.dedup();
+    let gaps = merge_gaps(&ticks, read_gaps(&gaps_path)?, series);
+
+    let mut content = String::from("time,bid,ask\n");
+    for tick in ticks.iter() {
+        content.push_str(&format!("{},{},{}\n", tick.time, tick.bid, tick.ask));
+    }
+    let mut gaps_content = String::from("start,end\n");
+    for gap in gaps.iter() {
+        gaps_content.push_str(&format!("{},{}\n", gap.start, gap.end));
+    }
+    fs::create_dir_all(DATA_DIR.as_path())?;
+    write_file(&path, content)?;
+    write_file(&gaps_path, gaps_content)?;
+    Ok(ticks.len())
+}
+
+// 读取已导入的序列和与该范围重叠的缺失区间，start 和 end 为毫秒时间，包含两端
+pub fn load_series(
+    symbol: &str,
+    kind: DataKind,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Result<(Vec<MarketTick>, Vec<Gap>)> {
+    if !get_symbols().iter().any(|s| s == symbol) {
+        return Err(Error::ErrorMessage(format!("Unknown symbol: {}", symbol)));
+    }
+    let ticks = read_ticks(&series_path(symbol, kind))?
+        .into_iter()
+        .filter(|t| start.is_none_or(|s| t.time >= s) && end.is_none_or(|e| t.time <= e))
+        .collect();
+    let gaps = read_gaps(&gaps_path(symbol, kind))?
+        .into_iter()
+        .filter(|g| start.is_none_or(|s| g.end > s) && end.is_none_or(|e| g.start < e))
+        .collect();
+    Ok((ticks, gaps))
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{
+        find_gaps, merge_gaps, parse_agg_trades, parse_book_tickers, parse_file_name, parse_klines,
+        DataKind, Gap, MarketSeries,
+    };
+    use crate::{backtest::market::MarketTick, error::Error};
+
+    fn error_line(e: Error) -> (String, u32, u32) {
+        match e {
+            Error::CustomError {
+                message,
+                line,
+                column,
+            } => (message, line, column),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(
+            parse_file_name("BTCUSDT-1m-2024-01-01.csv").unwrap(),
+            ("btcusdt".to_string(), DataKind::Kline, Some(60_000))
+        );
+        assert_eq!(
+            parse_file_name("ETHUSDT-bookTicker-2024-01.csv").unwrap(),
+            ("ethusdt".to_string(), DataKind::BookTicker, None)
+        );
+        assert_eq!(
+            parse_file_name("SOLUSDT-aggTrades-2024-01-01.csv")
+                .unwrap()
+                .1,
+            DataKind::AggTrade
+        );
+        assert!(parse_file_name("FOOUSDT-1m-2024-01-01.csv").is_err());
+        assert!(parse_file_name("BTCUSDT-1mo-2024-01.csv").is_err());
+    }
+
+    #[test]
+    fn test_parse_klines() {
+        let content = "open_time,open,high,low,close,volume,close_time,quote_volume,count,taker_buy_volume,taker_buy_quote_volume,ignore
+1704067200000,100,101,99,100.5,10,1704067259999,1000,5,5,500,0
+1704067260000000,100.5,102,100,101,10,1704067319999999,1000,5,5,500,0
+1704067440000,101,101.5,100.8,101.2,10,1704067499999,1000,5,5,500,0
+";
+        let klines = parse_klines(content).unwrap();
+        assert_eq!(klines.len(), 3);
+        // 微秒时间戳转换为毫秒
+        assert_eq!(klines[1].open_time, 1704067260000);
+        assert_eq!(klines[1].high, Decimal::new(102, 0));
+        // 缺少 1704067320000 和 1704067380000 两根 K 线
+        let gaps = find_gaps(klines.iter().map(|k| k.open_time), 60_000);
+        assert_eq!(
+            gaps,
+            vec![Gap {
+                start: 1704067260000,
+                end: 1704067440000
+            }]
+        );
+
+        let content = "1704067200000,100,99,98,100.5,10,1704067259999";
+        assert_eq!(
+            error_line(parse_klines(content).unwrap_err()),
+            ("high/low out of range".to_string(), 1, 3)
+        );
+        let content = "1704067200000,100,101,99,abc,10,1704067259999";
+        assert_eq!(
+            error_line(parse_klines(content).unwrap_err()),
+            ("invalid price".to_string(), 1, 5)
+        );
+    }
+
+    #[test]
+    fn test_parse_ticks() {
+        let content = "update_id,best_bid_price,best_bid_qty,best_ask_price,best_ask_qty,transaction_time,event_time
+1,100.1,5,100.2,3,1704067200001,1704067200002
+2,100.2,5,100.3,3,1704067200005,1704067200006
+";
+        let ticks = parse_book_tickers(content).unwrap();
+        assert_eq!(ticks.len(), 2);
+        assert_eq!(ticks[0].bid, Decimal::new(1001, 1));
+        assert_eq!(ticks[0].ask, Decimal::new(1002, 1));
+
+        let content = "1,100.3,5,100.2,3,1704067200001,1704067200002";
+        assert_eq!(
+            error_line(parse_book_tickers(content).unwrap_err()).0,
+            "bid above ask"
+        );
+
+        let content = "1,100,1,1,1,1704067200005,true
+2,100.1,1,2,2,1704067200001,false
+";
+        assert_eq!(
+            error_line(parse_agg_trades(content).unwrap_err()),
+            ("timestamp goes backwards".to_string(), 2, 1)
+        );
+    }
+
+    #[test]
+    fn test_merge_gaps() {
+        let tick = |time: i64| MarketTick {
+            time,
+            bid: Decimal::ONE,
+            ask: Decimal::ONE,
+        };
+        let series = |times: &[i64], gaps: Vec<Gap>| MarketSeries {
+            symbol: "btcusdt".to_string(),
+            kind: DataKind::AggTrade,
+            ticks: times.iter().map(|&t| tick(t)).collect(),
+            gaps,
+            max_interval: 10,
+        };
+
+        // 已保存 0..=20 和 100..=120，中间缺失；新数据填补 50..=60，两侧衔接处仍缺失
+        let stored = vec![Gap {
+            start: 20,
+            end: 100,
+        }];
+        let new = series(&[50, 55, 60], Vec::new());
+        let merged: Vec<_> = [0, 10, 20, 50, 55, 60, 100, 110, 120]
+            .into_iter()
+            .map(tick)
+            .collect();
+        assert_eq!(
+            merge_gaps(&merged, stored.clone(), &new),
+            vec![
+                Gap { start: 20, end: 50 },
+                Gap {
+                    start: 60,
+                    end: 100
+                }
+            ]
+        );
+
+        // 新数据完全填补缺失区间
+        let new = series(&[30, 40, 50, 60, 70, 80, 90], Vec::new());
+        let merged: Vec<_> = [0, 10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 110, 120]
+            .into_iter()
+            .map(tick)
+            .collect();
+        assert!(merge_gaps(&merged, stored, &new).is_empty());
+
+        // 新数据自身的缺失区间保留
+        let new = series(
+            &[200, 250],
+            vec![Gap {
+                start: 200,
+                end: 250,
+            }],
+        );
+        let merged: Vec<_> = [0, 10, 200, 250].into_iter().map(tick).collect();
+        assert_eq!(
+            merge_gaps(&merged, Vec::new(), &new),
+            vec![
+                Gap {
+                    start: 10,
+                    end: 200
+                },
+                Gap {
+                    start: 200,
+                    end: 250
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{data::Gap, market::MarketTick, stats::BacktestStats};
+use crate::static_items::{
+    position::{fee_break_even_price, Direction, ExitRules, Position, PositionAction},
+    stop_policy::{StopPolicyConfig, StopPolicyKind},
+    strategy::Strategy,
+};
+
+// 开仓信号，在 time 之后的第一个价格点按市价开仓
+#[derive(Deserialize, ToSchema, Debug, Clone)]
+pub struct Signal {
+    pub time: i64,
+    pub direction: Direction,
+    pub leverage: u8,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub margin: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub stop_loss_percent: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub initial_capital: Decimal,
+    pub taker_fee_rate: Decimal, // 开平仓都是市价单，按吃单费率计算
+    pub slippage: Decimal,       // 市价成交相对盘口价的滑点比例
+    pub stop_policy: StopPolicyConfig,
+    pub strategies: Vec<Strategy>,
+    pub exit_rules: ExitRules,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    StopLoss,   // 触发止损
+    TakeProfit, // 减仓档位平掉了全部仓位
+    TimeExit,   // 按时间平仓规则平仓
+    EndOfData,  // 数据结束时仍持有，按最后价格平仓
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct TradeResult {
+    pub entry_time: i64,
+    pub exit_time: i64,
+    pub direction: Direction,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub entry_price: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub exit_price: Decimal, // 按数量加权的平均平仓价
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub quantity: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub pnl: Decimal, // 扣除手续费后的盈亏
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub fees: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub r_multiple: Decimal, // 盈亏除以开仓时止损对应的亏损
+    pub exit_reason: ExitReason,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct BacktestReport {
+    pub trades: Vec<TradeResult>,
+    pub stats: BacktestStats,
+    pub gaps: Vec<Gap>, // 回测区间内已导入数据的缺失区间
+}
+
+// 回测中持有的仓位
+struct OpenTrade {
+    position: Position,
+    entry_time: i64,
+    initial_quantity: Decimal,
+    quantity: Decimal,   // 剩余数量
+    exit_value: Decimal, // 已平仓部分的成交额
+    gross_pnl: Decimal,
+    fees: Decimal,
+    risk: Decimal, // 开仓时止损对应的亏损金额
+}
+
+impl OpenTrade {
+    fn open(signal: &Signal, tick: &MarketTick, config: &BacktestConfig) -> Option<Self> {
+        if signal.leverage == 0 || signal.margin <= Decimal::ZERO {
+            return None;
+        }
+        let entry_price = fill_price(tick, signal.direction == Direction::Long, config.slippage);
+        if entry_price <= Decimal::ZERO {
+            return None;
+        }
+        let leverage = Decimal::from(signal.leverage);
+        let quantity = signal.margin * leverage / entry_price;
+        let mut position = Position::simulated(
+            signal.direction.clone(),
+            entry_price,
+            leverage,
+            signal.stop_loss_percent,
+            StopPolicyKind::from_config(config.stop_policy.clone(), config.strategies.clone()),
+        );
+        position.break_even_price =
+            fee_break_even_price(&signal.direction, entry_price, config.taker_fee_rate);
+        position.exit_rules = config.exit_rules.clone();
+        position.opened_at = tick.time;
+        let risk = (entry_price - position.stop_loss).abs() * quantity;
+
+        Some(OpenTrade {
+            position,
+            entry_time: tick.time,
+            initial_quantity: quantity,
+            quantity,
+            exit_value: Decimal::ZERO,
+            gross_pnl: Decimal::ZERO,
+            fees: entry_price * quantity * config.taker_fee_rate,
+            risk,
+        })
+    }
+
+    // 按成交价平掉指定数量，返回扣除平仓手续费后的盈亏
+    fn close(&mut self, price: Decimal, quantity: Decimal, fee_rate: Decimal) -> Decimal {
+        let quantity = quantity.min(self.quantity);
+        let pnl = match self.position.direction {
+            Direction::Long => (price - self.position.entry_price) * quantity,
+            Direction::Short => (self.position.entry_price - price) * quantity,
+        };
+        let fee = price * quantity * fee_rate;
+        self.quantity -= quantity;
+        self.exit_value += price * quantity;
+        self.gross_pnl += pnl;
+        self.fees += fee;
+        pnl - fee
+    }
+
+    // 剩余数量按当前买一价（做多）或卖一价（做空）计算的浮动盈亏
+    fn unrealized_pnl(&self, tick: &MarketTick) -> Decimal {
+        match self.position.direction {
+            Direction::Long => (tick.bid - self.position.entry_price) * self.quantity,
+            Direction::Short => (self.position.entry_price - tick.ask) * self.quantity,
+        }
+    }
+
+    fn result(&self, exit_time: i64, exit_reason: ExitReason) -> TradeResult {
+        let pnl = self.gross_pnl - self.fees;
+        let closed_quantity = self.initial_quantity - self.quantity;
+        let exit_price = if closed_quantity.is_zero() {
+            Decimal::ZERO
+        } else {
+            self.exit_value / closed_quantity
+        };
+        let r_multiple = if self.risk.is_zero() {
+            Decimal::ZERO
+        } else {
+            pnl / self.risk
+        };
+        TradeResult {
+            entry_time: self.entry_time,
+            exit_time,
+            direction: self.position.direction.clone(),
+            entry_price: self.position.entry_price.round_dp(8),
+            exit_price: exit_price.round_dp(8),
+            quantity: self.initial_quantity.round_dp(8),
+            pnl: pnl.round_dp(8),
+            fees: self.fees.round_dp(8),
+            r_multiple: r_multiple.round_dp(4),
+            exit_reason,
+        }
+    }
+}
+
+// 市价成交价，买入按卖一价加滑点，卖出按买一价减滑点
+fn fill_price(tick: &MarketTick, buy: bool, slippage: Decimal) -> Decimal {
+    if buy {
+        tick.ask * (Decimal::ONE + slippage)
+    } else {
+        tick.bid * (Decimal::ONE - slippage)
+    }
+}
+
+// 按时间顺序回放价格，信号触发时开仓，用与实盘相同的仓位逻辑移动止损和平仓
+pub fn run_backtest(
+    ticks: &[MarketTick],
+    signals: &[Signal],
+    config: &BacktestConfig,
+) -> BacktestReport {
+    let mut signals: Vec<&Signal> = signals.iter().collect();
+    signals.sort_by_key(|s| s.time);
+    let mut next_signal = 0;
+    let mut open: Vec<OpenTrade> = Vec::new();
+    let mut trades = Vec::new();
+    let mut equity = config.initial_capital;
+    // 权益曲线计入持仓的浮动盈亏，最大回撤包含止损成交前持仓承受的亏损
+    let mut equity_curve = vec![equity];
+
+    for tick in ticks {
+        // 先处理已有仓位，本价格点新开的仓位从下一个价格点开始跟踪
+        let mut i = 0;
+        while i < open.len() {
+            let trade = &mut open[i];
+            let is_long = trade.position.direction == Direction::Long;
+            // 做多按买一价、做空按卖一价判断，与实盘一致
+            let price = if is_long { tick.bid } else { tick.ask };
+            let (_, mut actions) = trade.position.apply_price(price, tick.time);
+            let mut reason = ExitReason::StopLoss;
+            if let Some(action) = trade.position.check_time_exit(tick.time, None) {
+                actions.push(action);
+                reason = ExitReason::TimeExit;
+            }
+
+            let exit_price = fill_price(tick, !is_long, config.slippage);
+            let mut exited = false;
+            for action in actions {
+                match action {
+                    // 回测中止损单总是立即重挂成功
+                    PositionAction::ReplaceStop => trade.position.stop_pending = false,
+                    PositionAction::PartialClose(fraction) => {
+                        // 减仓比例按开仓数量计算，超过剩余数量时全部平仓
+                        let quantity = trade.initial_quantity * fraction.min(Decimal::ONE);
+                        equity += trade.close(exit_price, quantity, config.taker_fee_rate);
+                        if trade.quantity.is_zero() {
+                            exited = true;
+                            reason = ExitReason::TakeProfit;
+                        }
+                    }
+                    PositionAction::Exit => {
+                        let quantity = trade.quantity;
+                        equity += trade.close(exit_price, quantity, config.taker_fee_rate);
+                        exited = true;
+                    }
+                }
+            }
+            if exited {
+                trades.push(open.remove(i).result(tick.time, reason));
+            } else {
+                i += 1;
+            }
+        }
+
+        while next_signal < signals.len() && signals[next_signal].time <= tick.time {
+            if let Some(trade) = OpenTrade::open(signals[next_signal], tick, config) {
+                equity -= trade.fees;
+                open.push(trade);
+            }
+            next_signal += 1;
+        }
+        let marked = equity + open.iter().map(|t| t.unrealized_pnl(tick)).sum::<Decimal>();
+        if equity_curve.last() != Some(&marked) {
+            equity_curve.push(marked);
+        }
+    }
+
+    if let Some(tick) = ticks.last() {
+        for mut trade in open.drain(..) {
+            let is_long = trade.position.direction == Direction::Long;
+            let exit_price = fill_price(tick, !is_long, config.slippage);
+            let quantity = trade.quantity;
+            equity += trade.close(exit_price, quantity, config.taker_fee_rate);
+            trades.push(trade.result(tick.time, ExitReason::EndOfData));
+        }
+        equity_curve.push(equity);
+    }
+    trades.sort_by_key(|t| t.entry_time);
+
+    let stats = BacktestStats::new(config.initial_capital, &trades, &equity_curve);
+    BacktestReport {
+        trades,
+        stats,
+        gaps: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{run_backtest, BacktestConfig, ExitReason, Signal};
+    use crate::{
+        backtest::market::MarketTick,
+        static_items::{
+            position::{Direction, ExitRules},
+            stop_policy::StopPolicyConfig,
+            strategy::Strategy,
+        },
+    };
+
+    fn d(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn ticks(prices: &[&str]) -> Vec<MarketTick> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| MarketTick {
+                time: (i as i64 + 1) * 1000,
+                bid: d(p),
+                ask: d(p),
+            })
+            .collect()
+    }
+
+    fn signal(time: i64, direction: Direction) -> Signal {
+        Signal {
+            time,
+            direction,
+            leverage: 10,
+            margin: d("100"),
+            stop_loss_percent: d("0.5"),
+        }
+    }
+
+    fn config(taker_fee_rate: &str) -> BacktestConfig {
+        BacktestConfig {
+            initial_capital: d("1000"),
+            taker_fee_rate: d(taker_fee_rate),
+            slippage: Decimal::ZERO,
+            stop_policy: StopPolicyConfig::Tiered,
+            strategies: vec![
+                Strategy {
+                    max: d("0.1"),
+                    adjustment: d("0.02"),
+                    close_fraction: Decimal::ZERO,
+                },
+                Strategy {
+                    max: d("0.2"),
+                    adjustment: d("0.04"),
+                    close_fraction: Decimal::ZERO,
+                },
+            ],
+            exit_rules: ExitRules::default(),
+        }
+    }
+
+    #[test]
+    fn test_run_backtest() {
+        let ticks = ticks(&["100", "101", "102", "100.3", "100", "104", "106", "90"]);
+        let signals = vec![
+            signal(1000, Direction::Long),
+            signal(5000, Direction::Short),
+        ];
+        let report = run_backtest(&ticks, &signals, &config("0"));
+
+        // 做多：止损移到 100.4 后在 100.3 平仓；做空：初始止损 105 在 106 平仓
+        let trades: Vec<(Decimal, Decimal, Decimal, ExitReason)> = report
+            .trades
+            .iter()
+            .map(|t| (t.exit_price, t.pnl, t.r_multiple, t.exit_reason))
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                (d("100.3"), d("3"), d("0.06"), ExitReason::StopLoss),
+                (d("106"), d("-60"), d("-1.2"), ExitReason::StopLoss),
+            ]
+        );
+
+        let stats = report.stats;
+        assert_eq!(stats.trades, 2);
+        assert_eq!(stats.wins, 1);
+        assert_eq!(stats.win_rate, d("0.5"));
+        assert_eq!(stats.total_return, d("-0.057"));
+        assert_eq!(stats.final_equity, d("943"));
+        assert_eq!(stats.average_r, d("-0.57"));
+        // 计入浮动盈亏后权益从 1020 回撤到 943
+        assert_eq!(stats.max_drawdown, (d("77") / d("1020")).round_dp(8));
+    }
+
+    #[test]
+    fn test_run_backtest_fees() {
+        let prices = ticks(&["100", "101", "102", "100.3"]);
+        let report = run_backtest(&prices, &[signal(0, Direction::Long)], &config("0.001"));
+
+        // 开仓手续费 1，平仓手续费 1.003
+        let trade = &report.trades[0];
+        assert_eq!(trade.fees, d("2.003"));
+        assert_eq!(trade.pnl, d("0.997"));
+        assert_eq!(report.stats.final_equity, d("1000.997"));
+
+        // 数据结束时仍持有的仓位按最后价格平仓
+        let prices = ticks(&["100", "101"]);
+        let report = run_backtest(&prices, &[signal(0, Direction::Long)], &config("0"));
+        assert_eq!(report.trades[0].exit_reason, ExitReason::EndOfData);
+        assert_eq!(report.trades[0].pnl, d("10"));
+    }
+
+    #[test]
+    fn test_run_backtest_partial_close() {
+        let mut config = config("0");
+        for strategy in config.strategies.iter_mut() {
+            strategy.close_fraction = d("0.25");
+        }
+
+        // 一次跨越两个档位：在 103 平掉开仓数量的一半，止损按第二档移到 100.4
+        let report = run_backtest(
+            &ticks(&["100", "103", "100.3"]),
+            &[signal(0, Direction::Long)],
+            &config,
+        );
+        let trade = &report.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.pnl, d("16.5"));
+
+        // 分两次跨越档位，每次都平掉开仓数量的 25%
+        let report = run_backtest(
+            &ticks(&["100", "101.5", "103", "100.3"]),
+            &[signal(0, Direction::Long)],
+            &config,
+        );
+        let trade = &report.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.pnl, d("12.75"));
+
+        // 校验允许的合计为 1 的减仓比例会平掉全部仓位
+        for strategy in config.strategies.iter_mut() {
+            strategy.close_fraction = d("0.5");
+        }
+        let report = run_backtest(
+            &ticks(&["100", "101.5", "103"]),
+            &[signal(0, Direction::Long)],
+            &config,
+        );
+        let trade = &report.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::TakeProfit);
+        assert_eq!(trade.pnl, d("22.5"));
+    }
+}
@@ -0,0 +1,80 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+// 回测使用的盘口价格，bid 为买一价，ask 为卖一价
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketTick {
+    pub time: i64, // 毫秒
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+// bookTicker 历史数据
+#[derive(Deserialize, ToSchema, Debug, Clone)]
+pub struct BookTicker {
+    pub time: i64,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub bid_price: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub ask_price: Decimal,
+}
+
+impl From<&BookTicker> for MarketTick {
+    fn from(ticker: &BookTicker) -> Self {
+        MarketTick {
+            time: ticker.time,
+            bid: ticker.bid_price,
+            ask: ticker.ask_price,
+        }
+    }
+}
+
+// K 线历史数据
+#[derive(Deserialize, ToSchema, Debug, Clone)]
+pub struct Kline {
+    pub open_time: i64,
+    pub close_time: i64,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub open: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub high: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub low: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub close: Decimal,
+}
+
+impl Kline {
+    // K 线内部的价格路径未知，阳线按 开-低-高-收、阴线按 开-高-低-收 拆成四个价格点
+    pub fn ticks(&self) -> [MarketTick; 4] {
+        let (first, second) = if self.close >= self.open {
+            (self.low, self.high)
+        } else {
+            (self.high, self.low)
+        };
+        let step = (self.close_time - self.open_time) / 3;
+        let tick = |i: i64, price: Decimal| MarketTick {
+            time: self.open_time + step * i,
+            bid: price,
+            ask: price,
+        };
+        [
+            tick(0, self.open),
+            tick(1, first),
+            tick(2, second),
+            tick(3, self.close),
+        ]
+    }
+}
+
+// 将 K 线序列展开为价格点
+pub fn kline_ticks(klines: &[Kline]) -> Vec<MarketTick> {
+    klines.iter().flat_map(|k| k.ticks()).collect()
+}
@@ -0,0 +1,5 @@
+pub mod data;
+pub mod engine;
+pub mod market;
+pub mod optimizer;
+pub mod stats;
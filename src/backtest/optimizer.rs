@@ -0,0 +1,545 @@
+use std::thread;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    data::Gap,
+    engine::{run_backtest, BacktestConfig, Signal},
+    market::MarketTick,
+    stats::BacktestStats,
+};
+use crate::{
+    error::{error_code, Error, Result},
+    static_items::strategy::{validate_tiers, Strategy},
+};
+
+// 单次优化允许的最大参数组合数量
+const MAX_CANDIDATES: usize = 10_000;
+// 计算收益回撤比时回撤的下限，避免除以 0
+const MIN_DRAWDOWN: Decimal = Decimal::from_parts(1, 0, 0, false, 4);
+
+// 参数取值范围，按 step 从 min 取到 max，step 为 0 时只取 min
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct ParameterRange {
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub min: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub max: Decimal,
+    #[serde(default, with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub step: Decimal,
+}
+
+impl ParameterRange {
+    fn values(&self) -> Vec<Decimal> {
+        let mut values = vec![self.min];
+        if self.step <= Decimal::ZERO {
+            return values;
+        }
+        let mut value = self.min + self.step;
+        while value <= self.max && values.len() <= MAX_CANDIDATES {
+            values.push(value);
+            value += self.step;
+        }
+        values
+    }
+}
+
+// 搜索空间，档位阈值和锁定收益按比例整体缩放基础档位
+#[derive(Deserialize, ToSchema, Debug, Clone)]
+pub struct SearchSpace {
+    pub tier_scale: ParameterRange,
+    pub adjustment_scale: ParameterRange,
+    pub stop_loss_percent: ParameterRange,
+    pub leverage: Vec<u8>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchMode {
+    Grid,                                 // 遍历全部组合
+    Random { samples: usize, seed: u64 }, // 从全部组合中随机抽取
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    TotalReturn,
+    AverageR,
+    WinRate,
+    #[default]
+    ReturnOverDrawdown, // 总收益率除以最大回撤
+}
+
+impl Objective {
+    pub fn score(&self, stats: &BacktestStats) -> Decimal {
+        match self {
+            Objective::TotalReturn => stats.total_return,
+            Objective::AverageR => stats.average_r,
+            Objective::WinRate => stats.win_rate,
+            Objective::ReturnOverDrawdown => {
+                (stats.total_return / stats.max_drawdown.max(MIN_DRAWDOWN)).round_dp(8)
+            }
+        }
+    }
+}
+
+// 一组待评估的参数
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct Candidate {
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub tier_scale: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub adjustment_scale: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub stop_loss_percent: Decimal,
+    pub leverage: u8,
+    pub tiers: Vec<Strategy>, // 缩放后的档位
+}
+
+impl Candidate {
+    fn new(
+        base: &[Strategy],
+        tier_scale: Decimal,
+        adjustment_scale: Decimal,
+        stop_loss_percent: Decimal,
+        leverage: u8,
+    ) -> Self {
+        let tiers = base
+            .iter()
+            .map(|s| Strategy {
+                max: s.max * tier_scale,
+                adjustment: s.adjustment * adjustment_scale,
+                close_fraction: s.close_fraction,
+            })
+            .collect();
+        Candidate {
+            tier_scale,
+            adjustment_scale,
+            stop_loss_percent,
+            leverage,
+            tiers,
+        }
+    }
+
+    fn evaluate(
+        &self,
+        ticks: &[MarketTick],
+        signals: &[Signal],
+        base: &BacktestConfig,
+    ) -> BacktestStats {
+        let signals: Vec<Signal> = signals
+            .iter()
+            .map(|s| Signal {
+                leverage: self.leverage,
+                stop_loss_percent: self.stop_loss_percent,
+                ..s.clone()
+            })
+            .collect();
+        let config = BacktestConfig {
+            strategies: self.tiers.clone(),
+            ..base.clone()
+        };
+        run_backtest(ticks, &signals, &config).stats
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizeOptions {
+    pub space: SearchSpace,
+    pub mode: SearchMode,
+    pub objective: Objective,
+    pub folds: usize, // 滚动验证的轮数，数据按时间分为 folds + 1 段
+    pub top: usize,   // 返回排名前几的参数
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct CandidateResult {
+    pub candidate: Candidate,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub in_sample_score: Decimal, // 各轮训练段得分的平均值，轮次胜出数相同时按其排名
+    pub fold_wins: usize, // 在训练段胜出的轮数，用于排名
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub out_of_sample_score: Decimal, // 各轮验证段得分的平均值，只用于检验，不参与排名
+    pub out_of_sample_trades: usize,
+}
+
+// 一轮滚动验证：在训练段选出最优参数，在紧随其后的验证段检验
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct FoldResult {
+    pub train_start: i64,
+    pub test_start: i64,
+    pub test_end: i64,
+    pub best: Candidate,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub in_sample_score: Decimal,
+    pub out_of_sample: BacktestStats,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OptimizeReport {
+    pub evaluated: usize,
+    pub ranking: Vec<CandidateResult>,
+    pub folds: Vec<FoldResult>,
+    pub gaps: Vec<Gap>, // 回测区间内已导入数据的缺失区间
+}
+
+// xorshift64* 随机数，固定种子时结果可复现
+struct Rng(u64);
+
+impl Rng {
+    // 用 splitmix64 打散种子，避免较小的种子产生相近的序列
+    fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng((z ^ (z >> 31)).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// 生成参数组合，缩放后档位校验不通过的组合被丢弃
+pub fn candidates(
+    base: &[Strategy],
+    space: &SearchSpace,
+    mode: &SearchMode,
+) -> Result<Vec<Candidate>> {
+    let tier_scales = space.tier_scale.values();
+    let adjustment_scales = space.adjustment_scale.values();
+    let stop_loss_percents = space.stop_loss_percent.values();
+    let leverages: Vec<u8> = space.leverage.iter().copied().filter(|l| *l > 0).collect();
+    let total =
+        tier_scales.len() * adjustment_scales.len() * stop_loss_percents.len() * leverages.len();
+    let invalid_space = || Error::ErrorCode(error_code::INVALID_SEARCH_SPACE.0);
+
+    // 按混合进制把序号映射为参数组合
+    let candidate = |mut index: usize| {
+        let t = tier_scales[index % tier_scales.len()];
+        index /= tier_scales.len();
+        let a = adjustment_scales[index % adjustment_scales.len()];
+        index /= adjustment_scales.len();
+        let s = stop_loss_percents[index % stop_loss_percents.len()];
+        index /= stop_loss_percents.len();
+        Candidate::new(base, t, a, s, leverages[index])
+    };
+    let indexes: Vec<usize> = match mode {
+        SearchMode::Grid => {
+            if total > MAX_CANDIDATES {
+                return Err(invalid_space());
+            }
+            (0..total).collect()
+        }
+        SearchMode::Random { samples, seed } => {
+            if total == 0 || *samples > MAX_CANDIDATES {
+                return Err(invalid_space());
+            }
+            let mut rng = Rng::new(*seed);
+            let mut indexes: Vec<usize> = (0..*samples).map(|_| rng.below(total)).collect();
+            indexes.sort_unstable();
+            indexes.dedup();
+            indexes
+        }
+    };
+
+    let candidates: Vec<Candidate> = indexes
+        .into_iter()
+        .map(candidate)
+        .filter(|c| c.stop_loss_percent > Decimal::ZERO && validate_tiers(&c.tiers).is_ok())
+        .collect();
+    if candidates.is_empty() {
+        return Err(invalid_space());
+    }
+    Ok(candidates)
+}
+
+// 在多个线程上并行评估
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let chunk = items.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(&f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("optimizer worker panicked"))
+            .collect()
+    })
+}
+
+// 时间在 [start, end) 内的行情和信号
+fn window<'a>(
+    ticks: &'a [MarketTick],
+    signals: &[Signal],
+    start: i64,
+    end: i64,
+) -> (&'a [MarketTick], Vec<Signal>) {
+    let from = ticks.partition_point(|t| t.time < start);
+    let to = ticks.partition_point(|t| t.time < end);
+    let signals = signals
+        .iter()
+        .filter(|s| s.time >= start && s.time < end)
+        .cloned()
+        .collect();
+    (&ticks[from..to], signals)
+}
+
+// 滚动验证：数据按时间分为 folds + 1 段，第 i 轮在第 i 段上评估全部参数、在第 i + 1 段上验证。
+// 按训练段胜出的轮数和训练段平均得分排名，验证段得分只作为检验结果返回，
+// 用它排名等于在验证数据上选参数，会高估实际表现
+pub fn optimize(
+    ticks: &[MarketTick],
+    signals: &[Signal],
+    base: &BacktestConfig,
+    options: &OptimizeOptions,
+) -> Result<OptimizeReport> {
+    let candidates = candidates(&base.strategies, &options.space, &options.mode)?;
+    let (first, last) = match (ticks.first(), ticks.last()) {
+        (Some(first), Some(last)) if options.folds > 0 && last.time > first.time => {
+            (first.time, last.time)
+        }
+        _ => return Err(Error::ErrorCode(error_code::INVALID_PRICE_SERIES.0)),
+    };
+    let segment = (last - first) / (options.folds as i64 + 1);
+    if segment == 0 {
+        return Err(Error::ErrorCode(error_code::INVALID_PRICE_SERIES.0));
+    }
+
+    let mut in_sample = vec![Decimal::ZERO; candidates.len()];
+    let mut out_of_sample = vec![Decimal::ZERO; candidates.len()];
+    let mut out_of_sample_trades = vec![0; candidates.len()];
+    let mut fold_wins = vec![0; candidates.len()];
+    let mut folds = Vec::with_capacity(options.folds);
+    for fold in 0..options.folds {
+        let train_start = first + segment * fold as i64;
+        let test_start = train_start + segment;
+        // 最后一段包含最后一个价格点
+        let test_end = if fold + 1 == options.folds {
+            last + 1
+        } else {
+            test_start + segment
+        };
+        let (train_ticks, train_signals) = window(ticks, signals, train_start, test_start);
+        let (test_ticks, test_signals) = window(ticks, signals, test_start, test_end);
+
+        let train: Vec<BacktestStats> = parallel_map(&candidates, |c| {
+            c.evaluate(train_ticks, &train_signals, base)
+        });
+        let test: Vec<BacktestStats> =
+            parallel_map(&candidates, |c| c.evaluate(test_ticks, &test_signals, base));
+
+        let mut best = 0;
+        let mut best_score = None;
+        for (i, (train, test)) in train.iter().zip(test.iter()).enumerate() {
+            let score = options.objective.score(train);
+            in_sample[i] += score;
+            out_of_sample[i] += options.objective.score(test);
+            out_of_sample_trades[i] += test.trades;
+            if best_score.is_none_or(|best| score > best) {
+                best = i;
+                best_score = Some(score);
+            }
+        }
+        fold_wins[best] += 1;
+        folds.push(FoldResult {
+            train_start,
+            test_start,
+            test_end,
+            best: candidates[best].clone(),
+            in_sample_score: best_score.unwrap_or_default(),
+            out_of_sample: test[best].clone(),
+        });
+    }
+
+    let count = Decimal::from(options.folds);
+    let evaluated = candidates.len();
+    let mut ranking: Vec<CandidateResult> = candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, candidate)| CandidateResult {
+            candidate,
+            in_sample_score: (in_sample[i] / count).round_dp(8),
+            fold_wins: fold_wins[i],
+            out_of_sample_score: (out_of_sample[i] / count).round_dp(8),
+            out_of_sample_trades: out_of_sample_trades[i],
+        })
+        .collect();
+    ranking.sort_by(|a, b| {
+        b.fold_wins
+            .cmp(&a.fold_wins)
+            .then(b.in_sample_score.cmp(&a.in_sample_score))
+    });
+    ranking.truncate(options.top.max(1));
+
+    Ok(OptimizeReport {
+        evaluated,
+        ranking,
+        folds,
+        gaps: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{
+        candidates, optimize, Objective, OptimizeOptions, ParameterRange, SearchMode, SearchSpace,
+    };
+    use crate::{
+        backtest::{
+            engine::{BacktestConfig, Signal},
+            market::MarketTick,
+        },
+        error::{error_code, Error},
+        static_items::{
+            position::{Direction, ExitRules},
+            stop_policy::StopPolicyConfig,
+            strategy::Strategy,
+        },
+    };
+
+    fn d(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn range(min: &str, max: &str, step: &str) -> ParameterRange {
+        ParameterRange {
+            min: d(min),
+            max: d(max),
+            step: d(step),
+        }
+    }
+
+    fn base_tiers() -> Vec<Strategy> {
+        vec![Strategy {
+            max: d("0.5"),
+            adjustment: d("0.1"),
+            close_fraction: Decimal::ZERO,
+        }]
+    }
+
+    #[test]
+    fn test_candidates() {
+        let space = SearchSpace {
+            tier_scale: range("1", "1.5", "0.5"),
+            adjustment_scale: range("1", "6", "5"),
+            stop_loss_percent: range("0.5", "0.5", "0"),
+            leverage: vec![5, 10],
+        };
+        // 锁定收益放大 6 倍后超过阈值的组合被丢弃
+        let grid = candidates(&base_tiers(), &space, &SearchMode::Grid).unwrap();
+        assert_eq!(grid.len(), 6);
+        assert!(grid.iter().all(|c| c.tiers[0].adjustment < c.tiers[0].max));
+
+        // 固定种子时随机搜索结果可复现
+        let random = SearchMode::Random {
+            samples: 4,
+            seed: 7,
+        };
+        let a = candidates(&base_tiers(), &space, &random).unwrap();
+        let b = candidates(&base_tiers(), &space, &random).unwrap();
+        assert!(a.len() <= 4);
+        assert_eq!(
+            a.iter()
+                .map(|c| (c.tier_scale, c.leverage))
+                .collect::<Vec<_>>(),
+            b.iter()
+                .map(|c| (c.tier_scale, c.leverage))
+                .collect::<Vec<_>>()
+        );
+
+        let space = SearchSpace {
+            stop_loss_percent: range("0.01", "1", "0.0001"),
+            ..space
+        };
+        match candidates(&base_tiers(), &space, &SearchMode::Grid) {
+            Err(Error::ErrorCode(code)) => assert_eq!(code, error_code::INVALID_SEARCH_SPACE.0),
+            r => panic!("unexpected result: {:?}", r.map(|c| c.len())),
+        }
+    }
+
+    #[test]
+    fn test_optimize() {
+        // 四段相同的行情：开仓后先回撤 2% 再上涨到 110
+        let prices = [
+            "100", "99", "98", "103", "106", "108", "110", "110", "110", "110",
+        ];
+        let mut ticks = Vec::new();
+        let mut signals = Vec::new();
+        for segment in 0..4 {
+            let base = segment * 10_000;
+            signals.push(Signal {
+                time: base,
+                direction: Direction::Long,
+                leverage: 10,
+                margin: d("100"),
+                stop_loss_percent: d("0.1"),
+            });
+            for (i, price) in prices.iter().enumerate() {
+                ticks.push(MarketTick {
+                    time: base + i as i64 * 1000,
+                    bid: d(price),
+                    ask: d(price),
+                });
+            }
+        }
+        let config = BacktestConfig {
+            initial_capital: d("1000"),
+            taker_fee_rate: Decimal::ZERO,
+            slippage: Decimal::ZERO,
+            stop_policy: StopPolicyConfig::Tiered,
+            strategies: base_tiers(),
+            exit_rules: ExitRules::default(),
+        };
+        let options = OptimizeOptions {
+            space: SearchSpace {
+                tier_scale: range("1", "1", "0"),
+                adjustment_scale: range("1", "1", "0"),
+                stop_loss_percent: range("0.1", "0.5", "0.4"),
+                leverage: vec![10],
+            },
+            mode: SearchMode::Grid,
+            objective: Objective::TotalReturn,
+            folds: 3,
+            top: 10,
+        };
+        let report = optimize(&ticks, &signals, &config, &options).unwrap();
+
+        // 止损过紧的参数在回撤时被止损，宽止损在每一轮训练段都胜出，按胜出轮数排名
+        assert_eq!(report.evaluated, 2);
+        assert_eq!(report.ranking[0].candidate.stop_loss_percent, d("0.5"));
+        assert_eq!(report.ranking[0].fold_wins, 3);
+        assert_eq!(report.ranking[1].fold_wins, 0);
+        assert!(report.ranking[0].in_sample_score > report.ranking[1].in_sample_score);
+        // 验证段得分只用于检验
+        assert!(report.ranking[0].out_of_sample_score > Decimal::ZERO);
+        assert!(report.ranking[1].out_of_sample_score < Decimal::ZERO);
+        assert_eq!(report.folds.len(), 3);
+        for fold in report.folds.iter() {
+            assert_eq!(fold.best.stop_loss_percent, d("0.5"));
+            assert_eq!(fold.out_of_sample.trades, 1);
+        }
+    }
+}
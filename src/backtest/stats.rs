@@ -0,0 +1,70 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::engine::TradeResult;
+
+// 回测汇总指标
+#[derive(Serialize, ToSchema, Debug, Clone, Default, PartialEq)]
+pub struct BacktestStats {
+    pub trades: usize,
+    pub wins: usize,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub win_rate: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub total_pnl: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub total_fees: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub total_return: Decimal, // 相对初始资金的收益率
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub max_drawdown: Decimal, // 计入持仓浮动盈亏的权益从高点回撤的最大比例
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub average_r: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub final_equity: Decimal,
+}
+
+impl BacktestStats {
+    pub fn new(initial_capital: Decimal, trades: &[TradeResult], equity_curve: &[Decimal]) -> Self {
+        let count = Decimal::from(trades.len().max(1));
+        let wins = trades.iter().filter(|t| t.pnl > Decimal::ZERO).count();
+        let total_pnl: Decimal = trades.iter().map(|t| t.pnl).sum();
+        let total_return = if initial_capital.is_zero() {
+            Decimal::ZERO
+        } else {
+            total_pnl / initial_capital
+        };
+        BacktestStats {
+            trades: trades.len(),
+            wins,
+            win_rate: (Decimal::from(wins) / count).round_dp(8),
+            total_pnl: total_pnl.round_dp(8),
+            total_fees: trades.iter().map(|t| t.fees).sum::<Decimal>().round_dp(8),
+            total_return: total_return.round_dp(8),
+            max_drawdown: max_drawdown(equity_curve).round_dp(8),
+            average_r: (trades.iter().map(|t| t.r_multiple).sum::<Decimal>() / count).round_dp(4),
+            final_equity: (initial_capital + total_pnl).round_dp(8),
+        }
+    }
+}
+
+// 权益曲线从历史高点回撤的最大比例
+pub fn max_drawdown(equity_curve: &[Decimal]) -> Decimal {
+    let mut peak = Decimal::ZERO;
+    let mut drawdown = Decimal::ZERO;
+    for equity in equity_curve {
+        peak = peak.max(*equity);
+        if peak > Decimal::ZERO {
+            drawdown = drawdown.max((peak - equity) / peak);
+        }
+    }
+    drawdown
+}
@@ -1,4 +1,8 @@
-use crate::error::Result;
+use super::{
+    rate_limit::Priority,
+    signed::{ApiKey, SignedRequest},
+};
+use crate::{error::Result, models::biance_model::CommissionRate};
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 
@@ -23,18 +27,17 @@ pub struct AccountInfo {
 
 #[allow(dead_code)]
 pub async fn get_account() -> Result<AccountInfo> {
-    let endpoint = format!("{}/fapi/v3/balance", super::BASE_URL);
-
-    // 获取当前时间戳
-    let timestamp = super::create_timestamp();
-
-    // 准备查询字符串并生成签名
-    let query_string = format!("timestamp={}", timestamp);
-    let signature = super::create_signature(&super::API_SECRET, &query_string);
-
-    // 完整请求 URL，包含签名
-    let url = format!("{}?{}&signature={}", endpoint, query_string, signature);
+    SignedRequest::new(Method::GET, "/fapi/v3/balance", &super::SYSTEM_KEY)
+        .priority(Priority::Low)
+        .send()
+        .await
+}
 
-    // 调用 get_request 发起请求并解析为 AccountInfo
-    super::request(&url, Method::GET, &super::API_KEY).await
+// 用户在该交易对的手续费率
+pub async fn get_commission_rate(symbol: &str, api_key: &ApiKey) -> Result<CommissionRate> {
+    SignedRequest::new(Method::GET, "/fapi/v1/commissionRate", api_key)
+        .priority(Priority::Low)
+        .param("symbol", symbol.to_uppercase())
+        .send()
+        .await
 }
@@ -0,0 +1,153 @@
+// 交易所环境配置，读取 config/services.toml 的 [exchange] 段，未配置时使用主网
+use std::{fs, sync::OnceLock};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::error::{Error, Result};
+
+static EXCHANGE_CONFIG: OnceLock<ExchangeConfig> = OnceLock::new();
+
+// 签名请求默认的 recvWindow，币安允许的最大值为 60000 毫秒
+const DEFAULT_RECV_WINDOW: u64 = 5000;
+const MAX_RECV_WINDOW: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExchangeProfile {
+    #[default]
+    Mainnet,
+    Testnet,
+    Mock, // 本地替身服务
+}
+
+impl ExchangeProfile {
+    fn rest_url(self) -> &'static str {
+        match self {
+            ExchangeProfile::Mainnet => "https://fapi.binance.com",
+            ExchangeProfile::Testnet => "https://testnet.binancefuture.com",
+            ExchangeProfile::Mock => "http://127.0.0.1:8090",
+        }
+    }
+
+    // 主网和测试网都订阅 U 本位合约行情，止损按与下单相同市场的价格计算
+    fn ws_url(self) -> &'static str {
+        match self {
+            ExchangeProfile::Mainnet => "wss://fstream.binance.com/ws",
+            ExchangeProfile::Testnet => "wss://fstream.binancefuture.com/ws",
+            ExchangeProfile::Mock => "ws://127.0.0.1:8090/ws",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExchangeConfig {
+    #[serde(default)]
+    pub profile: ExchangeProfile,
+    pub rest_url: Option<String>, // 覆盖 profile 的 REST 地址
+    pub ws_url: Option<String>,   // 覆盖 profile 的 websocket 地址
+    pub recv_window: Option<u64>, // 签名请求的有效时间窗口（毫秒）
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    exchange: ExchangeConfig,
+}
+
+impl ExchangeConfig {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(content)
+            .map_err(|e| Error::ErrorMessage(format!("Invalid exchange config: {}", e)))?;
+        let config = file.exchange;
+        for (url, schemes) in [
+            (config.rest_url(), ["http", "https"]),
+            (config.ws_url(), ["ws", "wss"]),
+        ] {
+            let valid = Url::parse(url).is_ok_and(|u| schemes.contains(&u.scheme()));
+            if !valid {
+                return Err(Error::ErrorMessage(format!(
+                    "Invalid exchange url: {}",
+                    url
+                )));
+            }
+        }
+        if !(1..=MAX_RECV_WINDOW).contains(&config.recv_window()) {
+            return Err(Error::ErrorMessage(format!(
+                "Invalid recv_window: {}",
+                config.recv_window()
+            )));
+        }
+        Ok(config)
+    }
+
+    pub fn recv_window(&self) -> u64 {
+        self.recv_window.unwrap_or(DEFAULT_RECV_WINDOW)
+    }
+
+    pub fn rest_url(&self) -> &str {
+        self.rest_url
+            .as_deref()
+            .unwrap_or(self.profile.rest_url())
+            .trim_end_matches('/')
+    }
+
+    pub fn ws_url(&self) -> &str {
+        self.ws_url
+            .as_deref()
+            .unwrap_or(self.profile.ws_url())
+            .trim_end_matches('/')
+    }
+}
+
+// 启动时加载，之后的币安请求和行情订阅都使用该配置
+pub fn init_exchange_config(path: &str) -> Result<()> {
+    let config = ExchangeConfig::from_toml(&fs::read_to_string(path)?)?;
+    println!(
+        "exchange profile {:?}, rest {}, ws {}",
+        config.profile,
+        config.rest_url(),
+        config.ws_url()
+    );
+    EXCHANGE_CONFIG
+        .set(config)
+        .map_err(|_| Error::SystemError("exchange config already initialized".to_owned()))
+}
+
+pub fn exchange_config() -> &'static ExchangeConfig {
+    EXCHANGE_CONFIG.get_or_init(ExchangeConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exchange_config() {
+        // 没有 [exchange] 段时使用主网
+        let config = ExchangeConfig::from_toml("[http]\nport = 9876\n").unwrap();
+        assert_eq!(config.profile, ExchangeProfile::Mainnet);
+        assert_eq!(config.rest_url(), "https://fapi.binance.com");
+        assert_eq!(config.ws_url(), "wss://fstream.binance.com/ws");
+
+        // 仓库中的配置文件可以正常解析
+        ExchangeConfig::from_toml(include_str!("../../config/services.toml")).unwrap();
+
+        let config = ExchangeConfig::from_toml("[exchange]\nprofile = \"testnet\"\n").unwrap();
+        assert_eq!(config.rest_url(), "https://testnet.binancefuture.com");
+        assert_eq!(config.ws_url(), "wss://fstream.binancefuture.com/ws");
+
+        let config = ExchangeConfig::from_toml(
+            "[exchange]\nprofile = \"mock\"\nrest_url = \"http://localhost:9000/\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.rest_url(), "http://localhost:9000");
+        assert_eq!(config.ws_url(), "ws://127.0.0.1:8090/ws");
+
+        assert_eq!(config.recv_window(), DEFAULT_RECV_WINDOW);
+
+        assert!(ExchangeConfig::from_toml("[exchange]\nprofile = \"staging\"\n").is_err());
+        assert!(ExchangeConfig::from_toml("[exchange]\nrecv_window = 60001\n").is_err());
+        assert!(ExchangeConfig::from_toml("[exchange]\nws_url = \"https://localhost\"\n").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+// 交易所抽象：交易流程只依赖 Exchange，真实币安账户、纸面交易账户和测试用的模拟交易所各自实现
+use futures_util::{future::BoxFuture, FutureExt};
+
+use super::{
+    account::get_commission_rate,
+    biance_trade::get_biance_risk,
+    leverage::{change_leverage, get_quantity_precision},
+    order::{
+        cancel_biance_order, create_biance_order, get_biance_active_order,
+        get_biance_finished_order, get_biance_order_by_client_id, CancelOrderResponse,
+    },
+    paper::PaperExchange,
+    signed::ApiKey,
+};
+use crate::{
+    error::Result,
+    models::biance_model::{
+        ActiveOrder, BiannceOrder, CommissionRate, ExchangeInfo, Leverage, Risk, TradeRecord,
+    },
+    static_items::secret_key::SecretKey,
+};
+
+// 下单参数，字段含义与币安 /fapi/v1/order 一致
+#[derive(Debug, Clone, Copy)]
+pub struct OrderRequest<'a> {
+    pub symbol: &'a str,
+    pub side: &'a str,          // BUY 或 SELL
+    pub position_side: &'a str, // LONG 或 SHORT
+    pub order_type: &'a str,    // MARKET 或 STOP_MARKET 等
+    pub quantity: &'a str,
+    pub price: Option<&'a str>, // 限价单价格，市价单为空
+    pub stop_price: Option<&'a str>,
+    pub client_order_id: Option<&'a str>, // 重发时可按该 ID 查询之前的订单
+}
+
+impl<'a> OrderRequest<'a> {
+    pub fn market(
+        symbol: &'a str,
+        side: &'a str,
+        position_side: &'a str,
+        quantity: &'a str,
+    ) -> Self {
+        OrderRequest {
+            symbol,
+            side,
+            position_side,
+            order_type: "MARKET",
+            quantity,
+            price: None,
+            stop_price: None,
+            client_order_id: None,
+        }
+    }
+
+    pub fn stop_market(
+        symbol: &'a str,
+        side: &'a str,
+        position_side: &'a str,
+        quantity: &'a str,
+        stop_price: &'a str,
+    ) -> Self {
+        OrderRequest {
+            order_type: "STOP_MARKET",
+            stop_price: Some(stop_price),
+            ..Self::market(symbol, side, position_side, quantity)
+        }
+    }
+
+    pub fn client_order_id(self, client_order_id: &'a str) -> Self {
+        OrderRequest {
+            client_order_id: Some(client_order_id),
+            ..self
+        }
+    }
+}
+
+pub trait Exchange: Send + Sync {
+    fn create_order<'a>(&'a self, order: OrderRequest<'a>) -> BoxFuture<'a, Result<ActiveOrder>>;
+
+    fn cancel_order<'a>(
+        &'a self,
+        symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<CancelOrderResponse>>;
+
+    // 订单成交均价和成交数量
+    fn get_order<'a>(
+        &'a self,
+        symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<BiannceOrder>>;
+
+    // 按 clientOrderId 查询订单，订单不存在时返回 None
+    fn find_order<'a>(
+        &'a self,
+        symbol: &'a str,
+        client_order_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<BiannceOrder>>>;
+
+    // 订单的逐笔成交记录
+    fn get_trades<'a>(
+        &'a self,
+        symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<Vec<TradeRecord>>>;
+
+    fn get_risk(&self) -> BoxFuture<'_, Result<Vec<Risk>>>;
+
+    fn change_leverage<'a>(
+        &'a self,
+        symbol: &'a str,
+        leverage: u32,
+    ) -> BoxFuture<'a, Result<Leverage>>;
+
+    fn commission_rate<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<CommissionRate>>;
+
+    fn exchange_info(&self) -> BoxFuture<'_, Result<ExchangeInfo>>;
+
+    // 成交手续费是否计入代理佣金，模拟成交不计入
+    fn records_fees(&self) -> bool {
+        true
+    }
+}
+
+// 币安 U 本位合约账户
+pub struct BinanceExchange {
+    api_key: ApiKey,
+}
+
+impl BinanceExchange {
+    pub fn new(api_key: ApiKey) -> Self {
+        BinanceExchange { api_key }
+    }
+}
+
+impl Exchange for BinanceExchange {
+    fn create_order<'a>(&'a self, order: OrderRequest<'a>) -> BoxFuture<'a, Result<ActiveOrder>> {
+        create_biance_order(order, &self.api_key).boxed()
+    }
+
+    fn cancel_order<'a>(
+        &'a self,
+        symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<CancelOrderResponse>> {
+        cancel_biance_order(symbol, order_id, &self.api_key).boxed()
+    }
+
+    fn get_order<'a>(
+        &'a self,
+        symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<BiannceOrder>> {
+        get_biance_active_order(symbol, order_id, &self.api_key).boxed()
+    }
+
+    fn find_order<'a>(
+        &'a self,
+        symbol: &'a str,
+        client_order_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<BiannceOrder>>> {
+        get_biance_order_by_client_id(symbol, client_order_id, &self.api_key).boxed()
+    }
+
+    fn get_trades<'a>(
+        &'a self,
+        symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<Vec<TradeRecord>>> {
+        get_biance_finished_order(symbol, order_id, &self.api_key).boxed()
+    }
+
+    fn get_risk(&self) -> BoxFuture<'_, Result<Vec<Risk>>> {
+        get_biance_risk(&self.api_key).boxed()
+    }
+
+    fn change_leverage<'a>(
+        &'a self,
+        symbol: &'a str,
+        leverage: u32,
+    ) -> BoxFuture<'a, Result<Leverage>> {
+        change_leverage(symbol, leverage, &self.api_key).boxed()
+    }
+
+    fn commission_rate<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<CommissionRate>> {
+        get_commission_rate(symbol, &self.api_key).boxed()
+    }
+
+    fn exchange_info(&self) -> BoxFuture<'_, Result<ExchangeInfo>> {
+        get_quantity_precision().boxed()
+    }
+}
+
+// 按用户的纸面交易设置选择交易所，纸面交易用户使用自己的模拟账户
+pub fn exchange(account: &SecretKey) -> Box<dyn Exchange> {
+    if account.paper_trading {
+        Box::new(PaperExchange::new(&account.id))
+    } else {
+        Box::new(BinanceExchange::new(account.api_key.clone()))
+    }
+}
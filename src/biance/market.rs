@@ -0,0 +1,10 @@
+use crate::{error::Result, models::biance_model::PremiumIndex};
+use reqwest::Method;
+
+// 全部交易对的标记价格和下一次资金费结算时间
+pub async fn get_premium_index() -> Result<Vec<PremiumIndex>> {
+    let endpoint = format!("{}/fapi/v1/premiumIndex", super::rest_url());
+    let response =
+        super::request::<Vec<PremiumIndex>>(&endpoint, Method::GET, &super::API_KEY).await?;
+    Ok(response)
+}
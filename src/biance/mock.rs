@@ -0,0 +1,233 @@
+// 测试用的内存交易所：按设定的价格立即成交，可模拟下单失败，复用纸面交易账户的撮合逻辑
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use rust_decimal::Decimal;
+
+use super::{
+    exchange::{Exchange, OrderRequest},
+    order::CancelOrderResponse,
+    paper::PaperAccount,
+};
+use crate::{
+    error::{Error, Result},
+    models::biance_model::{
+        ActiveOrder, BiannceOrder, CommissionRate, ExchangeInfo, Leverage, Risk, SymbolInfo,
+        TradeRecord,
+    },
+};
+
+pub struct MockExchange {
+    account: Mutex<PaperAccount>,
+    prices: Mutex<HashMap<String, Decimal>>, // key 为大写交易对
+    next_order_id: AtomicU64,
+    fail_orders: AtomicBool,
+}
+
+impl Default for MockExchange {
+    fn default() -> Self {
+        MockExchange {
+            account: Mutex::new(PaperAccount::new("mock")),
+            prices: Mutex::new(HashMap::new()),
+            next_order_id: AtomicU64::new(1),
+            fail_orders: AtomicBool::new(false),
+        }
+    }
+}
+
+impl MockExchange {
+    pub fn set_price(&self, symbol: &str, price: Decimal) {
+        let mut prices = self.prices.lock().unwrap();
+        prices.insert(symbol.to_uppercase(), price);
+    }
+
+    // 为 true 时所有下单请求都返回错误
+    pub fn set_fail_orders(&self, fail: bool) {
+        self.fail_orders.store(fail, Ordering::Relaxed);
+    }
+
+    pub fn account(&self) -> PaperAccount {
+        self.account.lock().unwrap().clone()
+    }
+
+    fn price(&self, symbol: &str) -> Result<Decimal> {
+        let prices = self.prices.lock().unwrap();
+        prices
+            .get(&symbol.to_uppercase())
+            .copied()
+            .ok_or_else(|| Error::ErrorMessage(format!("No price for {}", symbol)))
+    }
+}
+
+impl Exchange for MockExchange {
+    fn create_order<'a>(&'a self, order: OrderRequest<'a>) -> BoxFuture<'a, Result<ActiveOrder>> {
+        async move {
+            if self.fail_orders.load(Ordering::Relaxed) {
+                return Err(Error::ErrorMessage("Mock order failure".to_owned()));
+            }
+            let price = match order.order_type {
+                "MARKET" => self.price(order.symbol)?,
+                _ => Decimal::ZERO,
+            };
+            let order_id = self.next_order_id.fetch_add(1, Ordering::Relaxed);
+            self.account.lock().unwrap().submit(order_id, &order, price)
+        }
+        .boxed()
+    }
+
+    fn cancel_order<'a>(
+        &'a self,
+        _symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<CancelOrderResponse>> {
+        async move { self.account.lock().unwrap().cancel(order_id) }.boxed()
+    }
+
+    fn get_order<'a>(
+        &'a self,
+        _symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<BiannceOrder>> {
+        async move { self.account.lock().unwrap().order_status(order_id) }.boxed()
+    }
+
+    fn find_order<'a>(
+        &'a self,
+        _symbol: &'a str,
+        client_order_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<BiannceOrder>>> {
+        async move { Ok(self.account.lock().unwrap().find_order(client_order_id)) }.boxed()
+    }
+
+    fn get_trades<'a>(
+        &'a self,
+        _symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<Vec<TradeRecord>>> {
+        async move { self.account.lock().unwrap().trades(order_id) }.boxed()
+    }
+
+    fn get_risk(&self) -> BoxFuture<'_, Result<Vec<Risk>>> {
+        async move {
+            let prices = self.prices.lock().unwrap().clone();
+            Ok(self.account.lock().unwrap().risks(&prices))
+        }
+        .boxed()
+    }
+
+    fn change_leverage<'a>(
+        &'a self,
+        symbol: &'a str,
+        leverage: u32,
+    ) -> BoxFuture<'a, Result<Leverage>> {
+        async move { self.account.lock().unwrap().set_leverage(symbol, leverage) }.boxed()
+    }
+
+    fn commission_rate<'a>(&'a self, _symbol: &'a str) -> BoxFuture<'a, Result<CommissionRate>> {
+        async move {
+            Ok(CommissionRate {
+                taker_commission_rate: Decimal::new(5, 4),
+            })
+        }
+        .boxed()
+    }
+
+    fn exchange_info(&self) -> BoxFuture<'_, Result<ExchangeInfo>> {
+        async move {
+            let prices = self.prices.lock().unwrap();
+            let symbols = prices
+                .keys()
+                .map(|symbol| SymbolInfo {
+                    symbol: symbol.clone(),
+                    quantity_precision: 3,
+                    filters: Vec::new(),
+                })
+                .collect();
+            Ok(ExchangeInfo { symbols })
+        }
+        .boxed()
+    }
+
+    fn records_fees(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        cancel_stop_order, close_position_order, create_position_order, create_stop_order,
+        get_symbol_direction_quantity,
+    };
+
+    #[tokio::test]
+    async fn test_mock_trading_flow() {
+        let exchange = MockExchange::default();
+        exchange.set_price("btcusdt", Decimal::from(100000));
+        exchange.change_leverage("btcusdt", 10).await.unwrap();
+
+        let order = create_position_order(&exchange, "btcusdt", "BUY", "LONG", "0.02")
+            .await
+            .unwrap();
+        assert_eq!(order.avg_price, "100000");
+        assert_eq!(order.executed_qty, "0.02");
+
+        let stop = create_stop_order(&exchange, "btcusdt", "SELL", "LONG", "0.02", "95000")
+            .await
+            .unwrap();
+        assert_eq!(stop.status, "NEW");
+
+        exchange.set_price("btcusdt", Decimal::from(105000));
+        let quantity = get_symbol_direction_quantity(&exchange, "btcusdt", "LONG")
+            .await
+            .unwrap();
+        assert_eq!(quantity, "0.02");
+
+        cancel_stop_order(&exchange, "btcusdt", stop.order_id)
+            .await
+            .unwrap();
+        // 止损单撤销后不能重复撤销
+        assert!(cancel_stop_order(&exchange, "btcusdt", stop.order_id)
+            .await
+            .is_err());
+
+        let trades = close_position_order(&exchange, "u1", "btcusdt", "SELL", "LONG")
+            .await
+            .unwrap();
+        assert_eq!(
+            trades[0].realized_pnl.parse::<Decimal>().unwrap(),
+            Decimal::from(100)
+        );
+        assert!(exchange.get_risk().await.unwrap().is_empty());
+        assert_eq!(exchange.account().realized_pnl, Decimal::from(100));
+
+        // 下单失败时返回错误，不改变持仓
+        exchange.set_fail_orders(true);
+        assert!(
+            create_position_order(&exchange, "btcusdt", "BUY", "LONG", "0.02")
+                .await
+                .is_err()
+        );
+        assert!(exchange.account().positions.is_empty());
+
+        // 可按 clientOrderId 查询之前发送的订单
+        exchange.set_fail_orders(false);
+        let order =
+            OrderRequest::market("btcusdt", "BUY", "LONG", "0.01").client_order_id("exit_1_0");
+        exchange.create_order(order).await.unwrap();
+        let found = exchange.find_order("btcusdt", "exit_1_0").await.unwrap();
+        assert_eq!(found.unwrap().status, "FILLED");
+        assert!(exchange
+            .find_order("btcusdt", "exit_1_1")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,19 +1,26 @@
 // mod account;
 pub mod account;
 pub mod biance_trade;
+pub mod config;
+pub mod exchange;
 pub mod leverage;
+pub mod market;
+#[cfg(test)]
+pub mod mock;
 pub mod order;
+pub mod paper;
+pub mod rate_limit;
+pub mod signed;
 
 use crate::error::{Error, Result};
 use hmac::{Hmac, Mac};
 use lazy_static::lazy_static;
+use rate_limit::Priority;
 use reqwest::{Client, Method};
 use serde::de::DeserializeOwned;
 use sha2::Sha256;
-use std::{
-    env,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use signed::{ApiKey, KeyType};
+use std::env;
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -22,9 +29,13 @@ lazy_static! {
     pub static ref API_KEY: String = env::var("API_KEY").expect("API_KEY must be set in .env");
     pub static ref API_SECRET: String =
         env::var("API_SECRET").expect("API_SECRET must be set in .env");
+    pub static ref SYSTEM_KEY: ApiKey = ApiKey::new(&API_KEY, &API_SECRET, KeyType::Hmac);
 }
 
-const BASE_URL: &str = "https://fapi.binance.com";
+// 当前环境的 REST 地址
+fn rest_url() -> &'static str {
+    config::exchange_config().rest_url()
+}
 
 fn create_signature(secret: &str, query_string: &str) -> String {
     let mut mac =
@@ -33,7 +44,15 @@ fn create_signature(secret: &str, query_string: &str) -> String {
     hex::encode(mac.finalize().into_bytes())
 }
 
-pub async fn request<T: DeserializeOwned>(url: &str, method: Method, api_key: &str) -> Result<T> {
+// 按限频状态发送请求并返回响应文本，is_order 为 true 时计入账户下单数
+async fn send_request(
+    url: &str,
+    method: Method,
+    api_key: &str,
+    priority: Priority,
+    is_order: bool,
+) -> Result<String> {
+    rate_limit::acquire(api_key, priority, is_order).await?;
     let client = Client::new();
 
     // 根据方法构造请求
@@ -48,11 +67,18 @@ pub async fn request<T: DeserializeOwned>(url: &str, method: Method, api_key: &s
     // 添加通用头部
     let request_builder = request_builder.header("X-MBX-APIKEY", api_key);
 
-    // 发送请求并获取响应
-    let response_text = request_builder.send().await?.text().await?;
+    // 发送请求并获取响应，记录响应头中的用量
+    let response = request_builder.send().await?;
+    rate_limit::record(api_key, response.status(), response.headers())?;
+    let response_text = response.text().await?;
 
     // 打印响应内容
     // println!("Response content: {}", response_text);
+    Ok(response_text)
+}
+
+pub async fn request<T: DeserializeOwned>(url: &str, method: Method, api_key: &str) -> Result<T> {
+    let response_text = send_request(url, method, api_key, Priority::Normal, false).await?;
 
     // 解析响应为指定类型
     let response = serde_json::from_str::<T>(&response_text).map_err(|e| {
@@ -62,12 +88,3 @@ pub async fn request<T: DeserializeOwned>(url: &str, method: Method, api_key: &s
 
     Ok(response)
 }
-
-fn create_timestamp() -> String {
-    // 获取当前时间戳
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_millis()
-        .to_string()
-}
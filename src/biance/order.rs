@@ -1,138 +1,109 @@
+use super::{
+    exchange::OrderRequest,
+    rate_limit::Priority,
+    signed::{ApiKey, SignedRequest},
+};
 use crate::{
-    error::Result,
+    error::{Error, Result},
     models::biance_model::{ActiveOrder, BiannceOrder, TradeRecord},
 };
 use reqwest::Method;
 use serde::Deserialize;
 
-pub async fn create_biance_order(
-    symbol: &str,
-    side: &str,          // 买入或卖出： "BUY" 或 "SELL"
-    position_side: &str, // 仓位方向，例如 "LONG" 或 "SHORT"
-    order_type: &str,    // 订单类型，例如 "LIMIT" 或 "MARKET"
-    quantity: &str,      // 下单数量
-    price: Option<&str>, // 价格，市价单可为空
-    stop_price: Option<&str>,
-    key: &str,
-    secret: &str,
-) -> Result<ActiveOrder> {
-    let endpoint = format!("{}/fapi/v1/order", super::BASE_URL);
-
-    // 获取当前时间戳
-    let timestamp = super::create_timestamp();
-
-    // 构建查询字符串
-    let mut query_string = format!(
-        "symbol={}&side={}&positionSide={}&type={}&quantity={}&newOrderRespType={}&timestamp={}",
-        symbol, side, position_side, order_type, quantity, "RESULT", timestamp
-    );
-
-    if let Some(p) = price {
-        query_string.push_str(&format!("&price={}&timeInForce={}", p, "GTC"));
-    }
-    if let Some(sp) = stop_price {
-        query_string.push_str(&format!("&stopPrice={}", sp));
-    }
-
-    // 生成签名
-    let signature = super::create_signature(secret, &query_string);
-    let url = format!("{}?{}&signature={}", endpoint, query_string, signature);
-
-    // 使用 post_request 发送带 body 的请求
-    let response = super::request::<ActiveOrder>(&url, Method::POST, key).await?;
-
-    Ok(response)
+pub async fn create_biance_order(order: OrderRequest<'_>, api_key: &ApiKey) -> Result<ActiveOrder> {
+    SignedRequest::new(Method::POST, "/fapi/v1/order", api_key)
+        .param("symbol", order.symbol)
+        .param("side", order.side)
+        .param("positionSide", order.position_side)
+        .param("type", order.order_type)
+        .param("quantity", order.quantity)
+        .param("newOrderRespType", "RESULT")
+        .param_opt("price", order.price)
+        .param_opt("timeInForce", order.price.map(|_| "GTC"))
+        .param_opt("stopPrice", order.stop_price)
+        .param_opt("newClientOrderId", order.client_order_id)
+        .send()
+        .await
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CancelOrderResponse {
     // 根据 API 文档定义响应字段
-    orderId: u64,
+    #[serde(rename = "orderId")]
+    pub order_id: u64,
 }
 
-pub async fn cancel_biance_order(symbol: &str, order_id: u64) -> Result<CancelOrderResponse> {
-    let endpoint = format!("{}/fapi/v1/order", super::BASE_URL);
-
-    // 获取当前时间戳
-    let timestamp = super::create_timestamp();
-
-    // 准备查询字符串并生成签名
-    let query_string = format!(
-        "symbol={}&orderId={}&timestamp={}",
-        symbol, order_id, timestamp
-    );
-    let signature = super::create_signature(&super::API_SECRET, &query_string);
-
-    // 完整请求 URL，包含签名
-    let url = format!("{}?{}&signature={}", endpoint, query_string, signature);
-
-    // 调用 get_request 发起请求并解析为 AccountInfo
-    super::request::<CancelOrderResponse>(&url, Method::DELETE, &super::API_KEY).await
+pub async fn cancel_biance_order(
+    symbol: &str,
+    order_id: u64,
+    api_key: &ApiKey,
+) -> Result<CancelOrderResponse> {
+    SignedRequest::new(Method::DELETE, "/fapi/v1/order", api_key)
+        .param("symbol", symbol)
+        .param("orderId", order_id)
+        .send()
+        .await
 }
 
 pub async fn get_biance_orders(symbol: &str) -> Result<Vec<ActiveOrder>> {
-    let endpoint = format!("{}/fapi/v1/allOrders", super::BASE_URL);
-
-    // 获取当前时间戳
-    let timestamp = super::create_timestamp();
-
-    // 准备查询字符串并生成签名
-    let query_string = format!("symbol={}&timestamp={}", symbol, timestamp);
-    let signature = super::create_signature(&super::API_SECRET, &query_string);
-
-    // 完整请求 URL，包含签名
-    let url = format!("{}?{}&signature={}", endpoint, query_string, signature);
-
-    // 调用 get_request 发起请求并解析为 AccountInfo
-    super::request::<Vec<ActiveOrder>>(&url, Method::GET, &super::API_KEY).await
+    SignedRequest::new(Method::GET, "/fapi/v1/allOrders", &super::SYSTEM_KEY)
+        .priority(Priority::Low)
+        .param("symbol", symbol)
+        .send()
+        .await
 }
 
 pub async fn get_biance_active_order(
     symbol: &str,
     order_id: u64,
-    key: &str,
-    secret: &str,
+    api_key: &ApiKey,
 ) -> Result<BiannceOrder> {
-    let endpoint = format!("{}/fapi/v1/order", super::BASE_URL);
-
-    // 获取当前时间戳
-    let timestamp = super::create_timestamp();
+    SignedRequest::new(Method::GET, "/fapi/v1/order", api_key)
+        .param("symbol", symbol)
+        .param("orderId", order_id)
+        .send()
+        .await
+}
 
-    // 准备查询字符串并生成签名
-    let query_string = format!(
-        "symbol={}&orderId={}&timestamp={}",
-        symbol, order_id, timestamp
-    );
-    let signature = super::create_signature(secret, &query_string);
+// 币安返回的订单不存在错误码
+const ORDER_NOT_EXIST: i64 = -2013;
 
-    // 完整请求 URL，包含签名
-    let url = format!("{}?{}&signature={}", endpoint, query_string, signature);
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OrderQuery {
+    Order(BiannceOrder),
+    Error { code: i64, msg: String },
+}
 
-    // 调用 get_request 发起请求并解析为 AccountInfo
-    super::request(&url, Method::GET, key).await
+// 按下单时指定的 clientOrderId 查询订单，订单不存在时返回 None
+pub async fn get_biance_order_by_client_id(
+    symbol: &str,
+    client_order_id: &str,
+    api_key: &ApiKey,
+) -> Result<Option<BiannceOrder>> {
+    let query: OrderQuery = SignedRequest::new(Method::GET, "/fapi/v1/order", api_key)
+        .param("symbol", symbol)
+        .param("origClientOrderId", client_order_id)
+        .send()
+        .await?;
+    match query {
+        OrderQuery::Order(order) => Ok(Some(order)),
+        OrderQuery::Error { code, .. } if code == ORDER_NOT_EXIST => Ok(None),
+        OrderQuery::Error { code, msg } => Err(Error::ErrorMessage(format!(
+            "Query order failed: {} {}",
+            code, msg
+        ))),
+    }
 }
 
 pub async fn get_biance_finished_order(
     symbol: &str,
     order_id: u64,
-    key: &str,
-    secret: &str,
+    api_key: &ApiKey,
 ) -> Result<Vec<TradeRecord>> {
-    let endpoint = format!("{}/fapi/v1/userTrades", super::BASE_URL);
-
-    // 获取当前时间戳
-    let timestamp = super::create_timestamp();
-
-    // 准备查询字符串并生成签名
-    let query_string = format!(
-        "symbol={}&orderId={}&timestamp={}",
-        symbol, order_id, timestamp
-    );
-    let signature = super::create_signature(secret, &query_string);
-
-    // 完整请求 URL，包含签名
-    let url = format!("{}?{}&signature={}", endpoint, query_string, signature);
-
-    // 调用 get_request 发起请求并解析为 AccountInfo
-    super::request(&url, Method::GET, key).await
+    SignedRequest::new(Method::GET, "/fapi/v1/userTrades", api_key)
+        .param("symbol", symbol)
+        .param("orderId", order_id)
+        .send()
+        .await
 }
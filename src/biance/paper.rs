@@ -0,0 +1,675 @@
+// 纸面交易：模拟账户的订单在进程内按实时 bookTicker 成交，返回与币安接口相同的结构，
+// 通过 PaperExchange 接入，开仓、止损和对账逻辑无需区分真实账户
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, LazyLock,
+    },
+};
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use utoipa::{PartialSchema, ToSchema};
+
+use futures_util::{future::BoxFuture, FutureExt};
+
+use super::{
+    exchange::{Exchange, OrderRequest},
+    leverage::get_quantity_precision,
+    order::CancelOrderResponse,
+};
+use crate::{
+    database::paper_db::{db_delete_paper_account, db_get_paper_account, db_save_paper_account},
+    error::{Error, Result},
+    models::biance_model::{
+        ActiveOrder, BiannceOrder, CommissionRate, ExchangeInfo, Leverage, Risk, TradeRecord,
+    },
+    static_items::price::get_symbol_price,
+};
+
+// 模拟账户初始余额 10000 USDT
+pub const PAPER_INITIAL_BALANCE: Decimal = Decimal::from_parts(10000, 0, 0, false, 0);
+// 模拟吃单手续费率 0.05%
+const PAPER_TAKER_RATE: Decimal = Decimal::from_parts(5, 0, 0, false, 4);
+// 未设置杠杆时的默认杠杆
+const PAPER_DEFAULT_LEVERAGE: u32 = 20;
+// 每个账户保留的已成交订单数量，更早的成交记录会被丢弃
+const MAX_FILLED_ORDERS: usize = 200;
+
+// 全局锁只用于查找和插入账户，读写数据库时只持有单个账户的锁
+static ACCOUNTS: LazyLock<Mutex<HashMap<String, Arc<AccountSlot>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct AccountSlot {
+    account: Mutex<PaperAccount>,
+    save: Mutex<()>, // 同一账户的落库按顺序执行，每次写入最新状态
+}
+
+// 订单号从毫秒时间戳放大开始递增，不会与币安订单号和重启前的订单号冲突
+static ORDER_ID: LazyLock<AtomicU64> =
+    LazyLock::new(|| AtomicU64::new(Utc::now().timestamp_millis() as u64 * 1000));
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PaperPosition {
+    pub symbol: String,        // 大写交易对，与币安返回一致
+    pub position_side: String, // LONG 或 SHORT
+    #[schema(schema_with = String::schema)]
+    pub quantity: Decimal,
+    #[schema(schema_with = String::schema)]
+    pub entry_price: Decimal, // 开仓均价
+    pub leverage: u32,
+}
+
+impl PaperPosition {
+    fn unrealized_pnl(&self, mark_price: Decimal) -> Decimal {
+        match self.position_side.as_str() {
+            "SHORT" => (self.entry_price - mark_price) * self.quantity,
+            _ => (mark_price - self.entry_price) * self.quantity,
+        }
+    }
+
+    fn margin(&self) -> Decimal {
+        self.quantity * self.entry_price / Decimal::from(self.leverage.max(1))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperOrder {
+    pub order_id: u64,
+    pub symbol: String,
+    pub side: String,
+    pub position_side: String,
+    pub order_type: String,
+    pub quantity: Decimal,
+    pub price: Decimal,      // 成交价，未成交的止损单为 0
+    pub stop_price: Decimal, // 止损单触发价
+    pub status: String,      // NEW、FILLED 或 CANCELED
+    pub realized_pnl: Decimal,
+    pub commission: Decimal,
+    pub time: i64,
+    #[serde(default)]
+    pub client_order_id: String,
+}
+
+impl From<&PaperOrder> for ActiveOrder {
+    fn from(order: &PaperOrder) -> Self {
+        ActiveOrder {
+            order_id: order.order_id,
+            orig_qty: order.quantity.to_string(),
+            price: "0".to_owned(),
+            reduce_only: false,
+            side: order.side.clone(),
+            position_side: order.position_side.clone(),
+            status: order.status.clone(),
+            stop_price: order.stop_price.to_string(),
+            symbol: order.symbol.clone(),
+            time_in_force: "GTC".to_owned(),
+            order_type: order.order_type.clone(),
+            orig_type: order.order_type.clone(),
+            update_time: order.time,
+            working_type: "CONTRACT_PRICE".to_owned(),
+        }
+    }
+}
+
+impl From<&PaperOrder> for BiannceOrder {
+    fn from(order: &PaperOrder) -> Self {
+        BiannceOrder {
+            order_id: order.order_id,
+            avg_price: order.price.to_string(),
+            executed_qty: if order.status == "FILLED" {
+                order.quantity.to_string()
+            } else {
+                "0".to_owned()
+            },
+            status: order.status.clone(),
+        }
+    }
+}
+
+impl From<&PaperOrder> for TradeRecord {
+    fn from(order: &PaperOrder) -> Self {
+        TradeRecord {
+            buyer: order.side == "BUY",
+            commission: order.commission.to_string(),
+            commission_asset: "USDT".to_owned(),
+            id: order.order_id,
+            maker: false,
+            order_id: order.order_id,
+            price: order.price.to_string(),
+            qty: order.quantity.to_string(),
+            quote_qty: (order.price * order.quantity).to_string(),
+            realized_pnl: order.realized_pnl.to_string(),
+            side: order.side.clone(),
+            position_side: order.position_side.clone(),
+            symbol: order.symbol.clone(),
+            time: order.time as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperAccount {
+    pub user_id: String,
+    pub balance: Decimal, // 钱包余额：初始余额加已实现盈亏减手续费
+    pub realized_pnl: Decimal,
+    pub fees: Decimal,
+    pub positions: Vec<PaperPosition>,
+    #[serde(default)]
+    pub leverage: HashMap<String, u32>, // 各交易对的杠杆设置
+    #[serde(default)]
+    pub orders: Vec<PaperOrder>,
+}
+
+impl PaperAccount {
+    pub fn new(user_id: &str) -> Self {
+        PaperAccount {
+            user_id: user_id.to_owned(),
+            balance: PAPER_INITIAL_BALANCE,
+            realized_pnl: Decimal::ZERO,
+            fees: Decimal::ZERO,
+            positions: Vec::new(),
+            leverage: HashMap::new(),
+            orders: Vec::new(),
+        }
+    }
+
+    // 可用余额：钱包余额减去持仓占用的保证金
+    pub fn available(&self) -> Decimal {
+        self.balance - self.positions.iter().map(|p| p.margin()).sum::<Decimal>()
+    }
+
+    fn leverage_of(&self, symbol: &str) -> u32 {
+        self.leverage
+            .get(symbol)
+            .copied()
+            .unwrap_or(PAPER_DEFAULT_LEVERAGE)
+    }
+
+    // 按成交价撮合市价单：BUY LONG 和 SELL SHORT 开仓，反向单只能减仓
+    pub fn fill(
+        &mut self,
+        order_id: u64,
+        symbol: &str,
+        side: &str,
+        position_side: &str,
+        quantity: Decimal,
+        price: Decimal,
+    ) -> Result<&PaperOrder> {
+        if quantity <= Decimal::ZERO || price <= Decimal::ZERO {
+            return Err(Error::ErrorMessage("Invalid quantity or price.".to_owned()));
+        }
+        let opening = match (side, position_side) {
+            ("BUY", "LONG") | ("SELL", "SHORT") => true,
+            ("SELL", "LONG") | ("BUY", "SHORT") => false,
+            _ => {
+                return Err(Error::ErrorMessage(
+                    "Order's position side does not match user's setting.".to_owned(),
+                ))
+            }
+        };
+        let commission = quantity * price * PAPER_TAKER_RATE;
+        let index = self
+            .positions
+            .iter()
+            .position(|p| p.symbol == symbol && p.position_side == position_side);
+
+        let mut realized_pnl = Decimal::ZERO;
+        if opening {
+            let leverage = self.leverage_of(symbol);
+            let margin = quantity * price / Decimal::from(leverage);
+            if margin + commission > self.available() {
+                return Err(Error::ErrorMessage("Margin is insufficient.".to_owned()));
+            }
+            match index {
+                Some(index) => {
+                    let position = &mut self.positions[index];
+                    let total = position.quantity + quantity;
+                    position.entry_price =
+                        (position.entry_price * position.quantity + price * quantity) / total;
+                    position.quantity = total;
+                    position.leverage = leverage;
+                }
+                None => self.positions.push(PaperPosition {
+                    symbol: symbol.to_owned(),
+                    position_side: position_side.to_owned(),
+                    quantity,
+                    entry_price: price,
+                    leverage,
+                }),
+            }
+        } else {
+            let index = index
+                .filter(|&i| self.positions[i].quantity >= quantity)
+                .ok_or_else(|| Error::ErrorMessage("ReduceOnly Order is rejected.".to_owned()))?;
+            let position = &mut self.positions[index];
+            realized_pnl = position.unrealized_pnl(price) * quantity / position.quantity;
+            position.quantity -= quantity;
+            if position.quantity.is_zero() {
+                self.positions.remove(index);
+            }
+        }
+
+        self.balance += realized_pnl - commission;
+        self.realized_pnl += realized_pnl;
+        self.fees += commission;
+        self.push_order(PaperOrder {
+            order_id,
+            symbol: symbol.to_owned(),
+            side: side.to_owned(),
+            position_side: position_side.to_owned(),
+            order_type: "MARKET".to_owned(),
+            quantity,
+            price,
+            stop_price: Decimal::ZERO,
+            status: "FILLED".to_owned(),
+            realized_pnl,
+            commission,
+            time: Utc::now().timestamp_millis(),
+            client_order_id: String::new(),
+        });
+        Ok(self.orders.last().expect("order pushed"))
+    }
+
+    // 保存订单，已成交订单只保留最近的 MAX_FILLED_ORDERS 笔
+    fn push_order(&mut self, order: PaperOrder) {
+        self.orders.push(order);
+        let filled = self.orders.iter().filter(|o| o.status != "NEW").count();
+        if filled > MAX_FILLED_ORDERS {
+            let mut excess = filled - MAX_FILLED_ORDERS;
+            self.orders.retain(|o| {
+                if excess > 0 && o.status != "NEW" {
+                    excess -= 1;
+                    return false;
+                }
+                true
+            });
+        }
+    }
+
+    fn order(&self, order_id: u64) -> Result<&PaperOrder> {
+        self.orders
+            .iter()
+            .find(|o| o.order_id == order_id)
+            .ok_or_else(|| Error::ErrorMessage("Order does not exist.".to_owned()))
+    }
+
+    // 市价单按 price 立即成交；止损单只挂起，由仓位的止损检查在触发时市价平仓
+    pub fn submit(
+        &mut self,
+        order_id: u64,
+        order: &OrderRequest,
+        price: Decimal,
+    ) -> Result<ActiveOrder> {
+        let symbol = order.symbol.to_uppercase();
+        let quantity: Decimal = order
+            .quantity
+            .parse()
+            .map_err(|_| Error::ErrorMessage(format!("Invalid quantity {}", order.quantity)))?;
+        let client_order_id = order.client_order_id.unwrap_or_default().to_owned();
+        match order.order_type {
+            "MARKET" => {
+                self.fill(
+                    order_id,
+                    &symbol,
+                    order.side,
+                    order.position_side,
+                    quantity,
+                    price,
+                )?;
+                let order = self.orders.last_mut().expect("order pushed");
+                order.client_order_id = client_order_id;
+                Ok(ActiveOrder::from(&*order))
+            }
+            "STOP_MARKET" => {
+                let stop_price: Decimal = order
+                    .stop_price
+                    .and_then(|p| p.parse().ok())
+                    .ok_or_else(|| Error::ErrorMessage("Invalid stop price.".to_owned()))?;
+                let order = PaperOrder {
+                    order_id,
+                    symbol,
+                    side: order.side.to_owned(),
+                    position_side: order.position_side.to_owned(),
+                    order_type: order.order_type.to_owned(),
+                    quantity,
+                    price: Decimal::ZERO,
+                    stop_price,
+                    status: "NEW".to_owned(),
+                    realized_pnl: Decimal::ZERO,
+                    commission: Decimal::ZERO,
+                    time: Utc::now().timestamp_millis(),
+                    client_order_id,
+                };
+                let res = ActiveOrder::from(&order);
+                self.push_order(order);
+                Ok(res)
+            }
+            order_type => Err(Error::ErrorMessage(format!(
+                "Order type {} is not supported in paper trading",
+                order_type
+            ))),
+        }
+    }
+
+    // 撤销未触发的止损单
+    pub fn cancel(&mut self, order_id: u64) -> Result<CancelOrderResponse> {
+        let order = self
+            .orders
+            .iter_mut()
+            .find(|o| o.order_id == order_id && o.status == "NEW")
+            .ok_or_else(|| Error::ErrorMessage("Unknown order sent.".to_owned()))?;
+        order.status = "CANCELED".to_owned();
+        Ok(CancelOrderResponse { order_id })
+    }
+
+    pub fn order_status(&self, order_id: u64) -> Result<BiannceOrder> {
+        Ok(BiannceOrder::from(self.order(order_id)?))
+    }
+
+    pub fn find_order(&self, client_order_id: &str) -> Option<BiannceOrder> {
+        self.orders
+            .iter()
+            .find(|o| !o.client_order_id.is_empty() && o.client_order_id == client_order_id)
+            .map(BiannceOrder::from)
+    }
+
+    pub fn trades(&self, order_id: u64) -> Result<Vec<TradeRecord>> {
+        let order = self.order(order_id)?;
+        Ok(if order.status == "FILLED" {
+            vec![TradeRecord::from(order)]
+        } else {
+            Vec::new()
+        })
+    }
+
+    pub fn set_leverage(&mut self, symbol: &str, leverage: u32) -> Result<Leverage> {
+        if !(1..=125).contains(&leverage) {
+            return Err(Error::ErrorMessage("Leverage is not valid.".to_owned()));
+        }
+        let symbol = symbol.to_uppercase();
+        self.leverage.insert(symbol.clone(), leverage);
+        Ok(Leverage {
+            leverage,
+            max_notional_value: "0".to_owned(),
+            symbol,
+        })
+    }
+
+    // 按当前价格估算的币安持仓风险，mark_prices 的 key 为大写交易对
+    pub fn risks(&self, mark_prices: &HashMap<String, Decimal>) -> Vec<Risk> {
+        let now = Utc::now().timestamp_millis();
+        self.positions
+            .iter()
+            .map(|p| {
+                let mark_price = mark_prices.get(&p.symbol).copied().unwrap_or(p.entry_price);
+                let sign = if p.position_side == "SHORT" {
+                    Decimal::NEGATIVE_ONE
+                } else {
+                    Decimal::ONE
+                };
+                let rate = PAPER_TAKER_RATE;
+                Risk {
+                    symbol: p.symbol.clone(),
+                    position_side: p.position_side.clone(),
+                    position_amt: p.quantity * sign,
+                    entry_price: p.entry_price,
+                    // 开平仓各付一次吃单手续费后的保本价
+                    break_even_price: p.entry_price * (Decimal::ONE + sign * rate)
+                        / (Decimal::ONE - sign * rate),
+                    mark_price,
+                    unrealized_profit: p.unrealized_pnl(mark_price),
+                    notional: p.quantity * mark_price * sign,
+                    margin_asset: "USDT".to_owned(),
+                    initial_margin: p.margin(),
+                    position_initial_margin: p.margin(),
+                    update_time: now,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+}
+
+// 账户未加载时从数据库读取或按初始余额新建，并发加载时保留先插入的账户
+async fn account_slot(user_id: &str) -> Result<Arc<AccountSlot>> {
+    if let Some(slot) = ACCOUNTS.lock().await.get(user_id) {
+        return Ok(slot.clone());
+    }
+    let account = db_get_paper_account(user_id)
+        .await?
+        .unwrap_or_else(|| PaperAccount::new(user_id));
+    let mut accounts = ACCOUNTS.lock().await;
+    let slot = accounts.entry(user_id.to_owned()).or_insert_with(|| {
+        Arc::new(AccountSlot {
+            account: Mutex::new(account),
+            save: Mutex::new(()),
+        })
+    });
+    Ok(slot.clone())
+}
+
+// 在账户锁内执行 f；save 为 true 时释放账户锁后写回数据库
+async fn with_account<T>(
+    user_id: &str,
+    save: bool,
+    f: impl FnOnce(&mut PaperAccount) -> Result<T>,
+) -> Result<T> {
+    let slot = account_slot(user_id).await?;
+    let result = f(&mut *slot.account.lock().await)?;
+    if save {
+        // 排队落库时账户可能已被其他请求修改，写入取锁后的最新状态，避免旧状态覆盖新状态
+        let _save = slot.save.lock().await;
+        let account = slot.account.lock().await.clone();
+        // 内存中的成交已经生效，落库失败只记录日志
+        if let Err(e) = db_save_paper_account(account).await {
+            eprintln!("Save paper account error: {:?}", e);
+        }
+    }
+    Ok(result)
+}
+
+// 买入按卖一价成交，卖出按买一价成交
+async fn fill_price(symbol: &str, side: &str) -> Result<Decimal> {
+    let price = get_symbol_price(&symbol.to_lowercase()).await?;
+    let book = if side == "BUY" { price.buy } else { price.sell };
+    let price: Decimal = book
+        .parse()
+        .map_err(|_| Error::ErrorMessage(format!("Invalid price for {}", symbol)))?;
+    if price <= Decimal::ZERO {
+        return Err(Error::ErrorMessage(format!("No price for {}", symbol)));
+    }
+    Ok(price)
+}
+
+// 订单号在取价前分配，市价单按实时价格成交
+async fn create_order(user_id: &str, order: OrderRequest<'_>) -> Result<ActiveOrder> {
+    let order_id = ORDER_ID.fetch_add(1, Ordering::Relaxed);
+    let price = match order.order_type {
+        "MARKET" => fill_price(order.symbol, order.side).await?,
+        _ => Decimal::ZERO,
+    };
+    with_account(user_id, true, |account| {
+        account.submit(order_id, &order, price)
+    })
+    .await
+}
+
+pub async fn get_risk(user_id: &str) -> Result<Vec<Risk>> {
+    let account = get_account(user_id).await?;
+    let mut mark_prices = HashMap::new();
+    for position in account.positions.iter() {
+        if let Ok(price) = fill_price(&position.symbol, "SELL").await {
+            mark_prices.insert(position.symbol.clone(), price);
+        }
+    }
+    Ok(account.risks(&mark_prices))
+}
+
+// 纸面交易账户，行情和交易对信息仍来自币安
+pub struct PaperExchange {
+    user_id: String,
+}
+
+impl PaperExchange {
+    pub fn new(user_id: &str) -> Self {
+        PaperExchange {
+            user_id: user_id.to_owned(),
+        }
+    }
+}
+
+impl Exchange for PaperExchange {
+    fn create_order<'a>(&'a self, order: OrderRequest<'a>) -> BoxFuture<'a, Result<ActiveOrder>> {
+        create_order(&self.user_id, order).boxed()
+    }
+
+    fn cancel_order<'a>(
+        &'a self,
+        _symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<CancelOrderResponse>> {
+        with_account(&self.user_id, true, move |account| account.cancel(order_id)).boxed()
+    }
+
+    fn get_order<'a>(
+        &'a self,
+        _symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<BiannceOrder>> {
+        with_account(&self.user_id, false, move |account| {
+            account.order_status(order_id)
+        })
+        .boxed()
+    }
+
+    fn find_order<'a>(
+        &'a self,
+        _symbol: &'a str,
+        client_order_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<BiannceOrder>>> {
+        with_account(&self.user_id, false, move |account| {
+            Ok(account.find_order(client_order_id))
+        })
+        .boxed()
+    }
+
+    fn get_trades<'a>(
+        &'a self,
+        _symbol: &'a str,
+        order_id: u64,
+    ) -> BoxFuture<'a, Result<Vec<TradeRecord>>> {
+        with_account(&self.user_id, false, move |account| {
+            account.trades(order_id)
+        })
+        .boxed()
+    }
+
+    fn get_risk(&self) -> BoxFuture<'_, Result<Vec<Risk>>> {
+        get_risk(&self.user_id).boxed()
+    }
+
+    fn change_leverage<'a>(
+        &'a self,
+        symbol: &'a str,
+        leverage: u32,
+    ) -> BoxFuture<'a, Result<Leverage>> {
+        with_account(&self.user_id, true, move |account| {
+            account.set_leverage(symbol, leverage)
+        })
+        .boxed()
+    }
+
+    fn commission_rate<'a>(&'a self, _symbol: &'a str) -> BoxFuture<'a, Result<CommissionRate>> {
+        async {
+            Ok(CommissionRate {
+                taker_commission_rate: PAPER_TAKER_RATE,
+            })
+        }
+        .boxed()
+    }
+
+    fn exchange_info(&self) -> BoxFuture<'_, Result<ExchangeInfo>> {
+        get_quantity_precision().boxed()
+    }
+
+    fn records_fees(&self) -> bool {
+        false
+    }
+}
+
+pub async fn get_account(user_id: &str) -> Result<PaperAccount> {
+    with_account(user_id, false, |account| Ok(account.clone())).await
+}
+
+// 模拟账户恢复初始余额并清空持仓和订单
+pub async fn reset_account(user_id: &str) -> Result<()> {
+    let slot = account_slot(user_id).await?;
+    let _save = slot.save.lock().await;
+    let mut account = slot.account.lock().await;
+    db_delete_paper_account(user_id).await?;
+    *account = PaperAccount::new(user_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(
+        account: &mut PaperAccount,
+        symbol: &str,
+        side: &str,
+        position_side: &str,
+        quantity: &str,
+        price: &str,
+    ) -> Result<PaperOrder> {
+        let order_id = account.orders.len() as u64 + 1;
+        let (quantity, price) = (quantity.parse().unwrap(), price.parse().unwrap());
+        account
+            .fill(order_id, symbol, side, position_side, quantity, price)
+            .cloned()
+    }
+
+    #[test]
+    fn test_paper_fill() {
+        let mut account = PaperAccount::new("u1");
+        account.leverage.insert("BTCUSDT".to_owned(), 10);
+
+        // 开多 0.1，占用保证金 100000 * 0.1 / 10 = 1000
+        let order = fill(&mut account, "BTCUSDT", "BUY", "LONG", "0.1", "100000").unwrap();
+        assert_eq!(order.commission, Decimal::from(5));
+        assert_eq!(account.available(), Decimal::from(8995));
+
+        // 加仓后按数量加权计算均价
+        fill(&mut account, "BTCUSDT", "BUY", "LONG", "0.1", "102000").unwrap();
+        assert_eq!(account.positions[0].entry_price, Decimal::from(101000));
+
+        // 平掉一半，按均价计算已实现盈亏
+        let order = fill(&mut account, "BTCUSDT", "SELL", "LONG", "0.1", "103000").unwrap();
+        assert_eq!(order.realized_pnl, Decimal::from(200));
+        assert_eq!(account.positions[0].quantity, Decimal::new(1, 1));
+
+        // 平仓数量超过持仓时拒绝
+        assert!(fill(&mut account, "BTCUSDT", "SELL", "LONG", "0.2", "103000").is_err());
+
+        let order = fill(&mut account, "BTCUSDT", "SELL", "LONG", "0.1", "99000").unwrap();
+        assert_eq!(order.realized_pnl, Decimal::from(-200));
+        assert!(account.positions.is_empty());
+        assert_eq!(account.realized_pnl, Decimal::ZERO);
+        assert_eq!(account.balance, PAPER_INITIAL_BALANCE - account.fees);
+        assert_eq!(account.available(), account.balance);
+
+        // 做空按标记价格计算未实现盈亏，保证金不足时拒绝开仓
+        fill(&mut account, "ETHUSDT", "SELL", "SHORT", "1", "4000").unwrap();
+        let marks = HashMap::from([("ETHUSDT".to_owned(), Decimal::from(3900))]);
+        let risks = account.risks(&marks);
+        assert_eq!(risks[0].position_amt, Decimal::NEGATIVE_ONE);
+        assert_eq!(risks[0].unrealized_profit, Decimal::from(100));
+        assert!(fill(&mut account, "ETHUSDT", "SELL", "SHORT", "100", "4000").is_err());
+        assert!(fill(&mut account, "ETHUSDT", "BUY", "LONG", "-1", "4000").is_err());
+    }
+}
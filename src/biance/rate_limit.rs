@@ -0,0 +1,304 @@
+// 币安限频：按响应头记录 IP 请求权重和账户下单数，接近上限时低优先级请求被拒绝、普通请求排队到窗口重置，
+// 止损和平仓等高优先级请求始终发送；收到 429/418 后在 Retry-After 之前暂停请求
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{LazyLock, Mutex},
+};
+
+use chrono::Utc;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+use tokio::time::{sleep, Duration};
+
+use crate::error::{Error, Result};
+
+// IP 请求权重上限：(窗口毫秒, 上限)
+const WEIGHT_LIMITS: [(i64, u64); 1] = [(60_000, 2400)];
+// 账户下单数上限
+const ORDER_LIMITS: [(i64, u64); 2] = [(10_000, 300), (60_000, 1200)];
+// 用量达到上限的该百分比后，对应优先级的请求不再直接发送
+const LOW_THRESHOLD: u64 = 70;
+const NORMAL_THRESHOLD: u64 = 90;
+// 429/418 未返回 Retry-After 时的暂停时间
+const DEFAULT_RETRY_AFTER_MS: i64 = 60_000;
+// 被限频期间高优先级请求最多等待的时间，超过后直接返回错误
+const MAX_WAIT_MS: i64 = 60_000;
+
+static LIMITER: LazyLock<Mutex<RateLimiter>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,    // 持仓、手续费等查询，接近上限时拒绝
+    Normal, // 接近上限时等待窗口重置
+    High,   // 止损和平仓，始终发送
+}
+
+tokio::task_local! {
+    static TASK_PRIORITY: Priority;
+}
+
+// future 内发出的请求至少使用该优先级
+pub async fn with_priority<F: Future>(priority: Priority, f: F) -> F::Output {
+    TASK_PRIORITY.scope(priority, f).await
+}
+
+fn effective_priority(priority: Priority) -> Priority {
+    TASK_PRIORITY
+        .try_with(|p| (*p).max(priority))
+        .unwrap_or(priority)
+}
+
+// 币安的限频窗口按时间对齐，如 1m 窗口从每分钟开始
+#[derive(Debug, Default)]
+struct Usage {
+    counts: HashMap<i64, (i64, u64)>, // 窗口毫秒 -> (窗口序号, 已用量)
+}
+
+impl Usage {
+    fn record(&mut self, interval: i64, count: u64, now: i64) {
+        self.counts.insert(interval, (now / interval, count));
+    }
+
+    // 用量达到阈值的窗口中最晚的结束时间，都未达到时为空
+    fn blocked_until(&self, limits: &[(i64, u64)], threshold: u64, now: i64) -> Option<i64> {
+        limits
+            .iter()
+            .filter_map(|&(interval, limit)| {
+                let &(window, count) = self.counts.get(&interval)?;
+                (window == now / interval && count * 100 >= limit * threshold)
+                    .then_some((window + 1) * interval)
+            })
+            .max()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Permit {
+    Send,
+    Wait(i64), // 等待的毫秒数
+    Reject(String),
+}
+
+#[derive(Debug, Default)]
+struct RateLimiter {
+    ip: Usage,
+    accounts: HashMap<String, Usage>, // key 为 API Key
+    retry_at: i64,                    // 被限频或封禁后可以再次请求的时间
+}
+
+impl RateLimiter {
+    fn check(&self, key: &str, priority: Priority, is_order: bool, now: i64) -> Permit {
+        if now < self.retry_at {
+            let wait = self.retry_at - now;
+            return match priority {
+                Priority::High if wait <= MAX_WAIT_MS => Permit::Wait(wait),
+                _ => Permit::Reject(format!("rate limited for {}ms", wait)),
+            };
+        }
+        let threshold = match priority {
+            Priority::High => return Permit::Send,
+            Priority::Normal => NORMAL_THRESHOLD,
+            Priority::Low => LOW_THRESHOLD,
+        };
+        let mut until = self.ip.blocked_until(&WEIGHT_LIMITS, threshold, now);
+        if is_order {
+            if let Some(account) = self.accounts.get(key) {
+                until = until.max(account.blocked_until(&ORDER_LIMITS, threshold, now));
+            }
+        }
+        match (until, priority) {
+            (None, _) => Permit::Send,
+            (Some(until), Priority::Normal) => Permit::Wait(until - now),
+            (Some(_), _) => Permit::Reject("request weight near limit".to_owned()),
+        }
+    }
+
+    fn record(&mut self, key: &str, status: StatusCode, headers: &HeaderMap, now: i64) {
+        for (name, value) in headers {
+            let count = match value.to_str().ok().and_then(|v| v.parse::<u64>().ok()) {
+                Some(count) => count,
+                None => continue,
+            };
+            let name = name.as_str();
+            if let Some(interval) = name
+                .strip_prefix("x-mbx-used-weight-")
+                .and_then(parse_interval)
+            {
+                self.ip.record(interval, count, now);
+            } else if let Some(interval) = name
+                .strip_prefix("x-mbx-order-count-")
+                .and_then(parse_interval)
+            {
+                self.accounts
+                    .entry(key.to_owned())
+                    .or_default()
+                    .record(interval, count, now);
+            }
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+            let retry_after = headers
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<i64>().ok())
+                .map_or(DEFAULT_RETRY_AFTER_MS, |seconds| seconds * 1000);
+            self.retry_at = self.retry_at.max(now + retry_after);
+        }
+    }
+}
+
+// 响应头中的窗口长度，如 10s、1m
+fn parse_interval(interval: &str) -> Option<i64> {
+    let unit = match interval.chars().last()? {
+        's' => 1000,
+        'm' => 60_000,
+        'h' => 3_600_000,
+        'd' => 86_400_000,
+        _ => return None,
+    };
+    let count: i64 = interval[..interval.len() - 1].parse().ok()?;
+    Some(count * unit)
+}
+
+// 发送请求前按优先级检查用量，需要时等待，超过限制时返回错误
+pub async fn acquire(key: &str, priority: Priority, is_order: bool) -> Result<()> {
+    let priority = effective_priority(priority);
+    loop {
+        let now = Utc::now().timestamp_millis();
+        let permit = LIMITER.lock().unwrap().check(key, priority, is_order, now);
+        match permit {
+            Permit::Send => return Ok(()),
+            Permit::Wait(wait) => sleep(Duration::from_millis(wait as u64)).await,
+            Permit::Reject(reason) => {
+                return Err(Error::ErrorMessage(format!("Binance {}", reason)))
+            }
+        }
+    }
+}
+
+// 记录响应头中的用量，429/418 时返回错误
+pub fn record(key: &str, status: StatusCode, headers: &HeaderMap) -> Result<()> {
+    let now = Utc::now().timestamp_millis();
+    LIMITER.lock().unwrap().record(key, status, headers, now);
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+        return Err(Error::ErrorMessage(format!(
+            "Binance rate limit exceeded: {}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(values: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let mut limiter = RateLimiter::default();
+        let now = 120_000;
+        assert_eq!(limiter.check("a", Priority::Low, false, now), Permit::Send);
+
+        // 权重 1800/2400 超过低优先级阈值
+        limiter.record(
+            "a",
+            StatusCode::OK,
+            &headers(&[("x-mbx-used-weight-1m", "1800")]),
+            now,
+        );
+        assert!(matches!(
+            limiter.check("a", Priority::Low, false, now),
+            Permit::Reject(_)
+        ));
+        assert_eq!(
+            limiter.check("a", Priority::Normal, false, now),
+            Permit::Send
+        );
+
+        // 接近上限时普通请求等到下一分钟
+        limiter.record(
+            "a",
+            StatusCode::OK,
+            &headers(&[("x-mbx-used-weight-1m", "2200")]),
+            now + 1000,
+        );
+        assert_eq!(
+            limiter.check("a", Priority::Normal, false, now + 1000),
+            Permit::Wait(59_000)
+        );
+        assert_eq!(
+            limiter.check("a", Priority::High, false, now + 1000),
+            Permit::Send
+        );
+        assert_eq!(
+            limiter.check("a", Priority::Low, false, now + 60_000),
+            Permit::Send
+        );
+
+        // 下单数按账户统计，只影响下单请求
+        limiter.record(
+            "a",
+            StatusCode::OK,
+            &headers(&[
+                ("x-mbx-order-count-10s", "280"),
+                ("x-mbx-order-count-1m", "300"),
+            ]),
+            now + 60_000,
+        );
+        assert_eq!(
+            limiter.check("a", Priority::Normal, true, now + 65_000),
+            Permit::Wait(5000)
+        );
+        assert_eq!(
+            limiter.check("b", Priority::Normal, true, now + 65_000),
+            Permit::Send
+        );
+        assert_eq!(
+            limiter.check("a", Priority::Normal, false, now + 65_000),
+            Permit::Send
+        );
+
+        // 429 后在 Retry-After 之前只有高优先级请求等待
+        limiter.record(
+            "a",
+            StatusCode::TOO_MANY_REQUESTS,
+            &headers(&[("retry-after", "30")]),
+            now,
+        );
+        assert_eq!(
+            limiter.check("b", Priority::High, false, now),
+            Permit::Wait(30_000)
+        );
+        assert!(matches!(
+            limiter.check("b", Priority::Normal, false, now),
+            Permit::Reject(_)
+        ));
+        limiter.record(
+            "a",
+            StatusCode::IM_A_TEAPOT,
+            &HeaderMap::new(),
+            now + 30_000,
+        );
+        assert!(matches!(
+            limiter.check("b", Priority::High, false, now + 30_000),
+            Permit::Wait(60_000)
+        ));
+
+        // 任务范围内的优先级提升请求的优先级
+        assert_eq!(effective_priority(Priority::Low), Priority::Low);
+        let priority =
+            with_priority(Priority::High, async { effective_priority(Priority::Low) }).await;
+        assert_eq!(priority, Priority::High);
+    }
+}
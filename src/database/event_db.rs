@@ -0,0 +1,30 @@
+use service_utils_rs::services::db::get_db;
+
+use crate::{error::Result, models::event_model::CreatePositionEventRequest};
+
+pub async fn create_position_event_table() -> Result<()> {
+    let query = "
+    DEFINE TABLE IF NOT EXISTS position_event SCHEMALESS PERMISSIONS FULL;
+
+    DEFINE FIELD IF NOT EXISTS user_id ON TABLE position_event TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS order_id ON TABLE position_event TYPE int READONLY;
+    DEFINE FIELD IF NOT EXISTS symbol ON TABLE position_event TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS kind ON TABLE position_event TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS detail ON TABLE position_event TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS created_at ON TABLE position_event VALUE time::now() READONLY;
+
+    DEFINE INDEX IF NOT EXISTS user_id_index ON TABLE position_event FIELDS user_id;
+    DEFINE INDEX IF NOT EXISTS order_id_index ON TABLE position_event FIELDS order_id;
+    DEFINE INDEX IF NOT EXISTS created_at_index ON TABLE position_event FIELDS created_at;
+   ";
+
+    let db = get_db();
+    db.query(query).await?;
+    Ok(())
+}
+
+pub async fn db_create_position_event(input: CreatePositionEventRequest) -> Result<()> {
+    let db = get_db();
+    let _r: Option<CreatePositionEventRequest> = db.create("position_event").content(input).await?;
+    Ok(())
+}
@@ -1,14 +1,19 @@
 pub mod auth_db;
+pub mod event_db;
 pub mod fee_db;
 pub mod flow_db;
+pub mod paper_db;
 pub mod position_db;
 pub mod strategy_db;
 pub mod user_db;
 
 use crate::error::Result;
 use auth_db::create_auth_table;
+use event_db::create_position_event_table;
 use fee_db::create_fee_table;
-use strategy_db::create_strategy_table;
+use paper_db::create_paper_account_table;
+use position_db::create_position_table;
+use strategy_db::{create_strategy_table, create_strategy_version_table};
 use user_db::create_user_table;
 
 pub async fn create_tables() -> Result<()> {
@@ -16,5 +21,9 @@ pub async fn create_tables() -> Result<()> {
     create_fee_table().await?;
     create_user_table().await?;
     create_strategy_table().await?;
+    create_strategy_version_table().await?;
+    create_position_table().await?;
+    create_position_event_table().await?;
+    create_paper_account_table().await?;
     Ok(())
 }
@@ -0,0 +1,37 @@
+use service_utils_rs::services::db::get_db;
+
+use crate::{biance::paper::PaperAccount, error::Result};
+
+pub async fn create_paper_account_table() -> Result<()> {
+    let query = "
+    DEFINE TABLE IF NOT EXISTS paper_account SCHEMALESS PERMISSIONS FULL;
+
+    DEFINE FIELD IF NOT EXISTS user_id ON TABLE paper_account TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS created_at ON TABLE paper_account VALUE $before OR time::now();
+    DEFINE FIELD IF NOT EXISTS updated_at ON TABLE paper_account VALUE time::now();
+   ";
+
+    let db = get_db();
+    db.query(query).await?;
+    Ok(())
+}
+
+pub async fn db_get_paper_account(user_id: &str) -> Result<Option<PaperAccount>> {
+    let db = get_db();
+    let r: Option<PaperAccount> = db.select(("paper_account", user_id)).await?;
+    Ok(r)
+}
+
+pub async fn db_save_paper_account(account: PaperAccount) -> Result<()> {
+    let db = get_db();
+    let id = account.user_id.clone();
+    let _r: Option<PaperAccount> = db.upsert(("paper_account", id)).content(account).await?;
+    Ok(())
+}
+
+// 删除模拟账户，下次访问时按初始余额重建
+pub async fn db_delete_paper_account(user_id: &str) -> Result<()> {
+    let db = get_db();
+    let _r: Option<PaperAccount> = db.delete(("paper_account", user_id)).await?;
+    Ok(())
+}
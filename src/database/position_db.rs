@@ -1,24 +1,97 @@
+use std::{collections::HashMap, sync::LazyLock};
+
 use service_utils_rs::services::db::get_db;
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    Mutex,
+};
 
-use crate::error::Result;
+use crate::{error::Result, static_items::position::Position};
 
 pub async fn create_position_table() -> Result<()> {
     let query = "
-    DEFINE TABLE IF NOT EXISTS positon SCHEMALESS PERMISSIONS FULL;
-
-    DEFINE FIELD IF NOT EXISTS order_id ON TABLE positon TYPE string READONLY;
-    DEFINE FIELD IF NOT EXISTS user_id ON TABLE positon TYPE string READONLY;
-    DEFINE FIELD IF NOT EXISTS symbol ON TABLE positon TYPE string READONLY;
-    DEFINE FIELD IF NOT EXISTS key ON TABLE user TYPE string READONLY;
-    DEFINE FIELD IF NOT EXISTS secret ON TABLE user TYPE string READONLY;
-    DEFINE FIELD IF NOT EXISTS created_at ON TABLE positon VALUE time::now() READONLY;
-
-    DEFINE INDEX IF NOT EXISTS order_id_index ON TABLE positon FIELDS order_id;
-    DEFINE INDEX IF NOT EXISTS user_id_index ON TABLE positon FIELDS user_id;
-    DEFINE INDEX IF NOT EXISTS created_at_index ON TABLE positon FIELDS created_at;
+    DEFINE TABLE IF NOT EXISTS position SCHEMALESS PERMISSIONS FULL;
+
+    DEFINE FIELD IF NOT EXISTS order_id ON TABLE position TYPE int READONLY;
+    DEFINE FIELD IF NOT EXISTS user_id ON TABLE position TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS symbol ON TABLE position TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS status ON TABLE position TYPE string;
+    DEFINE FIELD IF NOT EXISTS created_at ON TABLE position VALUE $before OR time::now();
+    DEFINE FIELD IF NOT EXISTS updated_at ON TABLE position VALUE time::now();
+
+    DEFINE INDEX IF NOT EXISTS order_id_index ON TABLE position FIELDS order_id UNIQUE;
+    DEFINE INDEX IF NOT EXISTS user_id_index ON TABLE position FIELDS user_id;
+    DEFINE INDEX IF NOT EXISTS status_index ON TABLE position FIELDS status;
+    DEFINE INDEX IF NOT EXISTS created_at_index ON TABLE position FIELDS created_at;
+
+    UPDATE position SET status = IF is_closed THEN 'closed' ELSE 'open' END, is_closed = NONE WHERE status = NONE;
+    UPDATE position SET api_key = NONE, api_secret = NONE, api_key_type = NONE WHERE api_secret != NONE;
    ";
 
     let db = get_db();
     db.query(query).await?;
     Ok(())
 }
+
+pub async fn db_save_position(position: Position) -> Result<()> {
+    let db = get_db();
+    let id = position.order_id.to_string();
+    let _r: Option<Position> = db.upsert(("position", id)).content(position).await?;
+    Ok(())
+}
+
+pub async fn db_get_open_positions() -> Result<Vec<Position>> {
+    let db = get_db();
+    let query = "SELECT * FROM position WHERE status != 'closed';";
+    let mut r = db.query(query).await?;
+    let positions: Vec<Position> = r.take(0)?;
+    Ok(positions)
+}
+
+// 仓位写入队列，价格线程只负责投递，由单独的任务按顺序落库
+static POSITION_WRITER: LazyLock<PositionWriter> = LazyLock::new(PositionWriter::new);
+
+struct PositionWriter {
+    sender: UnboundedSender<Position>,
+    receiver: Mutex<Option<UnboundedReceiver<Position>>>,
+}
+
+impl PositionWriter {
+    fn new() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        PositionWriter {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+}
+
+// 投递仓位快照，不等待数据库
+pub fn save_position(position: Position) {
+    if let Err(e) = POSITION_WRITER.sender.send(position) {
+        eprintln!("save position error: {:?}", e.0.order_id);
+    }
+}
+
+pub async fn start_position_writer() {
+    let receiver = POSITION_WRITER.receiver.lock().await.take();
+    let mut receiver = match receiver {
+        Some(receiver) => receiver,
+        None => return,
+    };
+
+    while let Some(position) = receiver.recv().await {
+        // 合并同一仓位在队列中的多次变更，只写入最新状态
+        let mut pending = HashMap::new();
+        pending.insert(position.order_id, position);
+        while let Ok(position) = receiver.try_recv() {
+            pending.insert(position.order_id, position);
+        }
+
+        for (order_id, position) in pending {
+            if let Err(e) = db_save_position(position).await {
+                eprintln!("save position {} error: {:?}", order_id, e);
+            }
+        }
+    }
+}
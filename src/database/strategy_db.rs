@@ -2,18 +2,72 @@ use service_utils_rs::services::db::get_db;
 
 use crate::{
     error::{Error, Result},
-    models::trade_model::UpdateStrategy,
-    static_items::strategy::{StrategyConfig, UserStrategy},
+    static_items::strategy::{StrategyVersion, UserStrategy},
 };
 
 pub async fn create_strategy_table() -> Result<()> {
     let query = "
         DEFINE TABLE IF NOT EXISTS strategy SCHEMALESS PERMISSIONS FULL;
     
+        DEFINE FIELD IF NOT EXISTS strategy_id ON TABLE strategy TYPE string READONLY;
         DEFINE FIELD IF NOT EXISTS user_id ON TABLE strategy TYPE string READONLY;
-        DEFINE FIELD IF NOT EXISTS cfg ON TABLE strategy TYPE object;
-  
-        DEFINE INDEX IF NOT EXISTS unique_user_id ON TABLE strategy FIELDS user_id UNIQUE;
+        DEFINE FIELD IF NOT EXISTS name ON TABLE strategy TYPE string;
+        DEFINE FIELD IF NOT EXISTS tiers ON TABLE strategy TYPE array;
+        DEFINE FIELD IF NOT EXISTS version ON TABLE strategy TYPE int;
+        DEFINE FIELD IF NOT EXISTS version_id ON TABLE strategy TYPE string;
+        DEFINE FIELD IF NOT EXISTS created_at ON TABLE strategy VALUE $before OR time::now();
+        DEFINE FIELD IF NOT EXISTS updated_at ON TABLE strategy VALUE time::now();
+
+        REMOVE INDEX IF EXISTS unique_user_id ON TABLE strategy;
+        DEFINE INDEX IF NOT EXISTS unique_strategy_id ON TABLE strategy FIELDS strategy_id UNIQUE;
+        DEFINE INDEX IF NOT EXISTS user_id_index ON TABLE strategy FIELDS user_id;
+
+        -- 旧版本每个用户一条 s1/s2 记录，拆分为两条命名策略
+        FOR $old IN (SELECT * FROM strategy WHERE cfg != NONE) {
+            LET $s1 = <string> rand::uuid::v4();
+            LET $s2 = <string> rand::uuid::v4();
+            CREATE type::thing('strategy', $s1) CONTENT {
+                strategy_id: $s1, user_id: $old.user_id, name: 's1', tiers: $old.cfg.s1,
+                version: 1, version_id: <string> rand::uuid::v4()
+            };
+            CREATE type::thing('strategy', $s2) CONTENT {
+                strategy_id: $s2, user_id: $old.user_id, name: 's2', tiers: $old.cfg.s2,
+                version: 1, version_id: <string> rand::uuid::v4()
+            };
+            DELETE $old.id;
+        };
+        REMOVE FIELD IF EXISTS cfg ON TABLE strategy;
+       ";
+
+    let db = get_db();
+    db.query(query).await?;
+    Ok(())
+}
+
+pub async fn create_strategy_version_table() -> Result<()> {
+    let query = "
+        DEFINE TABLE IF NOT EXISTS strategy_version SCHEMALESS PERMISSIONS FULL;
+
+        DEFINE FIELD IF NOT EXISTS version_id ON TABLE strategy_version TYPE string READONLY;
+        DEFINE FIELD IF NOT EXISTS strategy_id ON TABLE strategy_version TYPE string READONLY;
+        DEFINE FIELD IF NOT EXISTS user_id ON TABLE strategy_version TYPE string READONLY;
+        DEFINE FIELD IF NOT EXISTS version ON TABLE strategy_version TYPE int READONLY;
+        DEFINE FIELD IF NOT EXISTS name ON TABLE strategy_version TYPE string READONLY;
+        DEFINE FIELD IF NOT EXISTS tiers ON TABLE strategy_version TYPE array READONLY;
+        DEFINE FIELD IF NOT EXISTS created_at ON TABLE strategy_version VALUE $before OR time::now();
+
+        DEFINE INDEX IF NOT EXISTS unique_version_id ON TABLE strategy_version FIELDS version_id UNIQUE;
+        DEFINE INDEX IF NOT EXISTS strategy_id_index ON TABLE strategy_version FIELDS strategy_id;
+
+        -- 没有版本记录的策略补一条当前版本
+        FOR $s IN (SELECT * FROM strategy WHERE version_id = NONE) {
+            LET $v = <string> rand::uuid::v4();
+            UPDATE $s.id SET version = 1, version_id = $v;
+            CREATE type::thing('strategy_version', $v) CONTENT {
+                version_id: $v, strategy_id: $s.strategy_id, user_id: $s.user_id,
+                version: 1, name: $s.name, tiers: $s.tiers
+            };
+        };
        ";
 
     let db = get_db();
@@ -21,33 +75,78 @@ pub async fn create_strategy_table() -> Result<()> {
     Ok(())
 }
 
-pub async fn db_create_strategy(user_id: &str) -> Result<UserStrategy> {
-    let input = UserStrategy {
-        user_id: user_id.to_string(),
-        cfg: StrategyConfig::default(),
-    };
+// 同时保存当前版本的快照
+pub async fn db_create_strategy(input: UserStrategy) -> Result<UserStrategy> {
+    db_create_strategy_version(StrategyVersion::from(&input)).await?;
     let db = get_db();
-    let r: Option<UserStrategy> = db.create(("strategy", user_id)).content(input).await?;
+    let r: Option<UserStrategy> = db
+        .create(("strategy", input.strategy_id.clone()))
+        .content(input)
+        .await?;
     match r {
         Some(r) => Ok(r),
         None => Err(Error::ErrorMessage("Create strategy failed".to_owned())),
     }
 }
 
-pub async fn db_get_strategy(user_id: &str) -> Result<UserStrategy> {
+pub async fn db_get_user_strategies(user_id: &str) -> Result<Vec<UserStrategy>> {
+    let db = get_db();
+    let mut r = db
+        .query("SELECT * FROM strategy WHERE user_id = $user_id ORDER BY created_at")
+        .bind(("user_id", user_id.to_string()))
+        .await?;
+    let strategies: Vec<UserStrategy> = r.take(0)?;
+    Ok(strategies)
+}
+
+// 调用前需通过 next_version 生成新版本号，同时保存该版本的快照
+pub async fn db_update_strategy(input: UserStrategy) -> Result<UserStrategy> {
+    db_create_strategy_version(StrategyVersion::from(&input)).await?;
     let db = get_db();
-    let r: Option<UserStrategy> = db.select(("strategy", user_id)).await?;
+    let r: Option<UserStrategy> = db
+        .update(("strategy", input.strategy_id.clone()))
+        .merge(input)
+        .await?;
     match r {
         Some(r) => Ok(r),
-        None => Err(Error::ErrorMessage("Get strategy failed".to_owned())),
+        None => Err(Error::ErrorMessage("Update strategy failed".to_owned())),
     }
 }
 
-pub async fn db_update_strategy(user_id: &str, input: UpdateStrategy) -> Result<UserStrategy> {
+pub async fn db_delete_strategy(strategy_id: &str) -> Result<()> {
+    let db = get_db();
+    let _: Option<UserStrategy> = db.delete(("strategy", strategy_id)).await?;
+    Ok(())
+}
+
+async fn db_create_strategy_version(input: StrategyVersion) -> Result<StrategyVersion> {
     let db = get_db();
-    let r: Option<UserStrategy> = db.update(("strategy", user_id)).content(input).await?;
+    let r: Option<StrategyVersion> = db
+        .create(("strategy_version", input.version_id.clone()))
+        .content(input)
+        .await?;
     match r {
         Some(r) => Ok(r),
-        None => Err(Error::ErrorMessage("Update strategy failed".to_owned())),
+        None => Err(Error::ErrorMessage(
+            "Create strategy version failed".to_owned(),
+        )),
     }
 }
+
+pub async fn db_get_strategy_versions(strategy_id: &str) -> Result<Vec<StrategyVersion>> {
+    let db = get_db();
+    let mut r = db
+        .query(
+            "SELECT * FROM strategy_version WHERE strategy_id = $strategy_id ORDER BY version DESC",
+        )
+        .bind(("strategy_id", strategy_id.to_string()))
+        .await?;
+    let versions: Vec<StrategyVersion> = r.take(0)?;
+    Ok(versions)
+}
+
+pub async fn db_get_strategy_version(version_id: &str) -> Result<Option<StrategyVersion>> {
+    let db = get_db();
+    let r: Option<StrategyVersion> = db.select(("strategy_version", version_id)).await?;
+    Ok(r)
+}
@@ -15,6 +15,8 @@ pub async fn create_user_table() -> Result<()> {
     DEFINE FIELD IF NOT EXISTS balance ON TABLE user TYPE decimal DEFAULT 0;
     DEFINE FIELD IF NOT EXISTS key ON TABLE user TYPE string READONLY;
     DEFINE FIELD IF NOT EXISTS secret ON TABLE user TYPE string READONLY;
+    DEFINE FIELD IF NOT EXISTS key_type ON TABLE user TYPE string DEFAULT 'hmac';
+    DEFINE FIELD IF NOT EXISTS paper_trading ON TABLE user TYPE bool DEFAULT false;
     DEFINE FIELD IF NOT EXISTS created_at ON TABLE user VALUE time::now() READONLY;
     DEFINE FIELD IF NOT EXISTS updated_at ON TABLE user VALUE time::now();
 
@@ -45,6 +47,18 @@ pub async fn db_get_user_info(user_id: &str) -> Result<User> {
     }
 }
 
+pub async fn db_update_paper_trading(user_id: &str, paper_trading: bool) -> Result<User> {
+    let db = get_db();
+    let r: Option<User> = db
+        .update(("user", user_id))
+        .merge(serde_json::json!({ "paper_trading": paper_trading }))
+        .await?;
+    match r {
+        Some(user) => Ok(user),
+        None => Err(Error::ErrorMessage("update user failed".to_owned())),
+    }
+}
+
 pub async fn db_update_player() -> Result<()> {
     let db = get_db();
     let input = UpdateUserInfoRequest {
@@ -9,6 +9,16 @@ macro_rules! status_error_codes {
             // 为每个元组生成一个常量定义
             pub const $konst: (u16, &str) = ($num, $phrase);
         )+
+
+        // 根据错误码查找对应的错误信息
+        pub fn error_phrase(code: u16) -> Option<(u16, &'static str)> {
+            $(
+                if code == $konst.0 {
+                    return Some($konst);
+                }
+            )+
+            None
+        }
     }
 }
 
@@ -19,4 +29,23 @@ status_error_codes! {
     (12, PASSWORD_ERROR, "password error");
     (13, INVALIAD_SYMBOLE, "invalid symbole");
     (14, USER_NOT_FOUND, "user not found");
+    (15, POSITION_NOT_FOUND, "position not found");
+    (16, POSITION_NOT_OPEN, "position is closing or closed");
+    (17, INVALID_STOP_LOSS, "invalid stop loss");
+    (18, STOP_LOSS_LOOSENED, "stop loss can only be tightened unless forced");
+    (19, INVALID_STOP_POLICY, "strategy tiers require a tiered stop policy");
+    (20, STRATEGY_NOT_FOUND, "strategy not found");
+    (21, STRATEGY_EMPTY, "strategy has no tiers");
+    (22, STRATEGY_TIER_UNSORTED, "tier max must be greater than the previous tier");
+    (23, STRATEGY_TIER_RANGE, "tier max must be positive and adjustment between 0 and max");
+    (24, STRATEGY_TIER_REGRESSED, "tier adjustment must not be lower than the previous tier");
+    (25, STRATEGY_CLOSE_FRACTION, "tier close fractions of the opening quantity must be in [0, 1] and sum to at most 1");
+    (26, STRATEGY_VERSION_NOT_FOUND, "strategy version not found");
+    (27, INVALID_PRICE_SERIES, "price series is empty, too long or contains invalid prices");
+    (28, INVALID_DATA_FILE, "data file is missing or invalid");
+    (29, INVALID_SEARCH_SPACE, "search space is empty or has too many combinations");
+    (30, POSITIONS_STILL_OPEN, "close all positions before switching paper trading");
+    (31, PAPER_TRADING_DISABLED, "paper trading is not enabled");
+    (32, INVALID_API_SECRET, "api secret does not match the key type");
+    (33, STOP_POLICY_PARAMS, "stop policy parameters are out of range for the leverage");
 }
@@ -0,0 +1,267 @@
+use axum::{http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
+
+use crate::{
+    backtest::{
+        self,
+        data::{load_csv, load_series, raw_file_path, save_series, DataKind, Gap},
+        engine::{run_backtest, BacktestConfig, Signal},
+        market::{kline_ticks, MarketTick},
+        optimizer::OptimizeOptions,
+    },
+    error::{error_code, Error},
+    models::{
+        backtest_model::{
+            ImportDataRequest, ImportDataResponse, ImportDataResult, OptimizeRequest,
+            OptimizeResponse, RunBacktestRequest, RunBacktestResponse,
+        },
+        CommonError, CommonResponse, IntoCommonResponse,
+    },
+    static_items::strategy::{default_tiers, get_user_strategy, validate_tiers, Strategy},
+};
+
+#[utoipa::path(
+    post,
+    path = "/run",
+    request_body = RunBacktestRequest,
+    responses(
+        (status = 200, description = "Succeed", body = RunBacktestResponse),
+        (status = 400, description = "Invalid strategy or market data", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "用历史行情和开仓信号回测止损策略"
+)]
+pub async fn run(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<RunBacktestRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let input = backtest_input(&user_id, payload, Vec::new()).await?;
+    // 回测是纯计算，放到阻塞线程避免占用异步运行时
+    let mut report = tokio::task::spawn_blocking(move || {
+        run_backtest(&input.ticks, &input.signals, &input.config)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Backtest error: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    })?;
+
+    report.gaps = input.gaps;
+
+    let res = report.into_common_response_data();
+    Ok(Json(res))
+}
+
+#[utoipa::path(
+    post,
+    path = "/optimize",
+    request_body = OptimizeRequest,
+    responses(
+        (status = 200, description = "Succeed", body = OptimizeResponse),
+        (status = 400, description = "Invalid search space or market data", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "按参数组合并行回测，按滚动验证中训练段胜出的轮数和训练段得分排名，样本外得分只用于检验，未指定档位时优化默认档位"
+)]
+pub async fn optimize(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<OptimizeRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let options = OptimizeOptions {
+        space: payload.space,
+        mode: payload.mode,
+        objective: payload.objective,
+        folds: payload.folds,
+        top: payload.top,
+    };
+    let input = backtest_input(&user_id, payload.backtest, default_tiers()).await?;
+    let mut report = tokio::task::spawn_blocking(move || {
+        backtest::optimizer::optimize(&input.ticks, &input.signals, &input.config, &options)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Optimize error: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    })?
+    .map_err(|e| match e {
+        Error::ErrorCode(code) => (
+            StatusCode::BAD_REQUEST,
+            Json(
+                error_code::error_phrase(code)
+                    .unwrap_or(error_code::SERVER_ERROR)
+                    .into(),
+            ),
+        ),
+        e => {
+            eprintln!("Optimize error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        }
+    })?;
+    report.gaps = input.gaps;
+
+    let res = report.into_common_response_data();
+    Ok(Json(res))
+}
+
+struct BacktestInput {
+    ticks: Vec<MarketTick>,
+    gaps: Vec<Gap>, // 使用已导入数据时回测区间内的缺失区间
+    signals: Vec<Signal>,
+    config: BacktestConfig,
+}
+
+// 读取已导入的数据，未指定类型时使用第一个有数据的类型
+fn load_imported(
+    symbol: &str,
+    kind: Option<DataKind>,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Result<(Vec<MarketTick>, Vec<Gap>), Error> {
+    if let Some(kind) = kind {
+        return load_series(symbol, kind, start, end);
+    }
+    for kind in DataKind::PREFERRED {
+        let (ticks, gaps) = load_series(symbol, kind, start, end)?;
+        if !ticks.is_empty() {
+            return Ok((ticks, gaps));
+        }
+    }
+    Ok((Vec::new(), Vec::new()))
+}
+
+// 解析回测请求中的行情、信号和配置，未指定档位时使用 default_strategies
+async fn backtest_input(
+    user_id: &str,
+    payload: RunBacktestRequest,
+    default_strategies: Vec<Strategy>,
+) -> Result<BacktestInput, (StatusCode, Json<CommonError>)> {
+    let (ticks, gaps) = match (payload.book_tickers, payload.klines, payload.symbol) {
+        (Some(tickers), _, _) => (tickers.iter().map(MarketTick::from).collect(), Vec::new()),
+        (None, Some(klines), _) => (kline_ticks(&klines), Vec::new()),
+        (None, None, Some(symbol)) => {
+            let (kind, start, end) = (payload.data_kind, payload.start_time, payload.end_time);
+            tokio::task::spawn_blocking(move || load_imported(&symbol, kind, start, end))
+                .await
+                .map_err(|e| {
+                    eprintln!("Load series error: {:?}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(error_code::SERVER_ERROR.into()),
+                    )
+                })?
+                .map_err(|e| {
+                    eprintln!("Load series error: {:?}", e);
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(error_code::INVALID_DATA_FILE.into()),
+                    )
+                })?
+        }
+        (None, None, None) => (Vec::new(), Vec::new()),
+    };
+    if ticks.is_empty() || payload.initial_capital <= Decimal::ZERO {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(error_code::INVALID_PRICE_SERIES.into()),
+        ));
+    }
+
+    let strategies = match (payload.strategies, payload.strategy_id) {
+        (Some(strategies), _) => {
+            validate_tiers(&strategies).map_err(|e| (StatusCode::BAD_REQUEST, Json(e.into())))?;
+            strategies
+        }
+        (None, Some(strategy_id)) => {
+            get_user_strategy(user_id, &strategy_id)
+                .await
+                .ok_or_else(|| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(error_code::STRATEGY_NOT_FOUND.into()),
+                    )
+                })?
+                .tiers
+        }
+        (None, None) => default_strategies,
+    };
+
+    let config = BacktestConfig {
+        initial_capital: payload.initial_capital,
+        taker_fee_rate: payload.taker_fee_rate,
+        slippage: payload.slippage,
+        stop_policy: payload.stop_policy,
+        strategies,
+        exit_rules: payload.exit_rules,
+    };
+    Ok(BacktestInput {
+        ticks,
+        gaps,
+        signals: payload.signals,
+        config,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/import",
+    request_body = ImportDataRequest,
+    responses(
+        (status = 200, description = "Succeed", body = ImportDataResponse),
+        (status = 400, description = "Invalid data file", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "导入 Binance K 线、bookTicker 或 aggTrades CSV，合并到该交易对同类型的回测数据并保存缺失区间"
+)]
+pub async fn import(
+    Json(payload): Json<ImportDataRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let invalid_file = |message: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(CommonError {
+                code: error_code::INVALID_DATA_FILE.0,
+                message,
+            }),
+        )
+    };
+    let path = raw_file_path(&payload.file_name)
+        .ok_or_else(|| invalid_file(error_code::INVALID_DATA_FILE.1.to_string()))?;
+
+    let result = tokio::task::spawn_blocking(move || {
+        let series = load_csv(&path)?;
+        let stored = save_series(&series)?;
+        Ok::<_, Error>(ImportDataResult {
+            symbol: series.symbol,
+            kind: series.kind,
+            count: series.ticks.len(),
+            start_time: series.ticks.first().map_or(0, |t| t.time),
+            end_time: series.ticks.last().map_or(0, |t| t.time),
+            gaps: series.gaps,
+            stored,
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Import data error: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    })?
+    .map_err(|e| {
+        eprintln!("Import data error: {:?}", e);
+        invalid_file(format!("{}: {}", error_code::INVALID_DATA_FILE.1, e))
+    })?;
+
+    let res = result.into_common_response_data();
+    Ok(Json(res))
+}
@@ -1,4 +1,5 @@
 pub mod auth_handler;
+pub mod backtest_handler;
 pub mod fee_handler;
 pub mod record_handler;
 pub mod trade_handler;
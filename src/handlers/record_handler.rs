@@ -1,19 +1,26 @@
 use axum::{extract::Query, http::StatusCode, Extension, Json};
 
 use crate::{
-    models::{record_model::GetPositionsRequest, CommonError, CommonResponse, IntoCommonResponse},
-    static_items::position::{get_user_symbol_direction_positions, Position},
+    models::{
+        record_model::GetPositionsRequest,
+        trade_model::{GetPositionGroupsResponse, PositionGroupData},
+        CommonError, CommonResponse, IntoCommonResponse,
+    },
+    static_items::position::get_user_symbol_direction_positions,
 };
 
 #[utoipa::path(
     get,
     path = "/get_positions",
-    params(("symbol" = String, Query, description = "货币符号比如:btcusdt"),),
+    params(
+        ("symbol" = String, Query, description = "货币符号比如:btcusdt"),
+        ("dirction" = String, Query, description = "方向: Long 或 Short"),
+    ),
     responses(
-        (status = 200, description = "Succeed", body = CommonResponse),
+        (status = 200, description = "Succeed", body = GetPositionGroupsResponse),
         (status = 500, description = "Internal server error", body = CommonError)
     ),
-    description = "用户某种货币本程序当前持仓情况"
+    description = "用户某种货币本程序当前持仓情况，同一方向的多笔仓位汇总显示"
 )]
 pub async fn get_positions(
     Extension(user_id): Extension<String>,
@@ -21,12 +28,7 @@ pub async fn get_positions(
 ) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
     let positions =
         get_user_symbol_direction_positions(&params.symbol, &params.dirction, &user_id).await;
-    // 根据 user_id 过滤 positions
-    let filtered_positions: Vec<Position> = positions
-        .into_iter()
-        .filter(|pos| pos.user_id == user_id) // 只保留 user_id 匹配的 Position
-        .collect();
 
-    let res = filtered_positions.into_common_response_data();
+    let res = PositionGroupData::group(&positions).into_common_response_data();
     Ok(Json(res))
 }
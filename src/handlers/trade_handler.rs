@@ -1,28 +1,52 @@
 use crate::{
-    biance::{biance_trade::get_biance_risk, leverage::change_leverage},
-    database::strategy_db::db_update_strategy,
-    error::error_code,
+    biance::{
+        exchange::exchange,
+        rate_limit::{with_priority, Priority},
+    },
+    database::{
+        position_db::save_position,
+        strategy_db::{
+            db_create_strategy, db_delete_strategy, db_get_strategy_version,
+            db_get_strategy_versions, db_update_strategy,
+        },
+    },
+    error::{error_code, Error},
     models::{
         trade_model::{
-            ClosePositionRequest, CreatePositionRequest, GetRiskResponse, GetStategyResponse,
-            RiskData, UpdateStrategy,
+            ClosePositionRequest, CreatePositionRequest, CreateStrategyRequest,
+            DeleteStrategyRequest, GetRiskResponse, GetStategyResponse, GetStrategyVersionsRequest,
+            GetStrategyVersionsResponse, PositionData, PositionGroupData, RiskData,
+            RollbackStrategyRequest, SimulateStrategyRequest, SimulateStrategyResponse,
+            SimulationData, SimulationStepData, StrategyResponse, UpdatePositionRequest,
+            UpdatePositionResponse, UpdateStrategyRequest,
         },
         CommonError, CommonResponse, IntoCommonResponse,
     },
     static_items::{
         percision::get_symbol_percision,
         position::{
-            get_user_symbol_direction_positions, inser_user_positon,
-            remove_user_symbol_direction_position, Direction, Position,
+            claim_user_position_exit, get_user_symbol_direction_positions, inser_user_positon,
+            release_position_exit, remove_user_position, remove_user_symbol_direction_position,
+            update_user_positions, Direction, Position, PositionUpdate,
         },
         price::get_symbol_price,
         secret_key::get_secret_key,
-        strategy::{get_user_spec_strategy, get_user_strategy, update_user_strategy},
+        stop_policy::StopPolicyKind,
+        strategy::{
+            get_user_strategies, get_user_strategy, remove_user_strategy, upsert_user_strategy,
+            validate_tiers, UserStrategy,
+        },
+    },
+    utils::{
+        calculate_quantity, close_position_order, close_position_quantity, create_position_order,
     },
-    utils::{calculate_quantity, close_position_order, create_position_order},
 };
 use axum::{http::StatusCode, Extension, Json};
 use chrono::DateTime;
+use rust_decimal::Decimal;
+
+// 单次模拟允许的最大价格数量
+const MAX_SIMULATION_PRICES: usize = 100_000;
 
 #[utoipa::path(
     get,
@@ -42,14 +66,12 @@ pub async fn get_risk(
             Json(error_code::SERVER_ERROR.into()),
         )
     })?;
-    let data = get_biance_risk(&secret_key.key, &secret_key.secret)
-        .await
-        .map_err(|_e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(error_code::SERVER_ERROR.into()),
-            )
-        })?;
+    let data = exchange(&secret_key).get_risk().await.map_err(|_e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    })?;
 
     println!("data: {:?}", data);
 
@@ -62,21 +84,26 @@ pub async fn get_risk(
             )
         })?;
         let update_time = DateTime::from_timestamp_millis(risk.update_time).unwrap();
-        let position =
+        let positions =
             get_user_symbol_direction_positions(&risk.symbol.to_lowercase(), &direction, &user_id)
                 .await;
+        // 同一方向的多笔仓位汇总显示
+        let group = PositionGroupData::group(&positions).pop();
 
         let stop_loss;
         let quantity;
+        let status;
 
-        match position {
-            Some(p) => {
-                stop_loss = p.stop_loss.to_string();
-                quantity = p.quantity.to_string();
+        match group {
+            Some(g) => {
+                stop_loss = g.stop_loss;
+                quantity = g.quantity;
+                status = Some(g.status);
             }
             None => {
                 stop_loss = risk.liquidation_price.to_string();
                 quantity = risk.position_amt.to_string();
+                status = None;
             }
         }
 
@@ -87,6 +114,7 @@ pub async fn get_risk(
             entry_price: risk.entry_price.to_string(),
             stop_price: stop_loss,
             quantity,
+            status,
             update_time,
         });
     }
@@ -102,17 +130,42 @@ pub async fn get_risk(
         (status = 200, description = "Succeed", body = GetStategyResponse),
         (status = 500, description = "Internal server error", body = CommonError)
     ),
-    description = "用户当前策略"
+    description = "用户保存的全部策略"
 )]
 pub async fn get_strategy(
     Extension(user_id): Extension<String>,
 ) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
-    let strategy = get_user_strategy(&user_id).await.ok_or_else(|| {
+    let strategies = get_user_strategies(&user_id).await.unwrap_or_default();
+
+    let res = strategies.into_common_response_data();
+    Ok(Json(res))
+}
+
+#[utoipa::path(
+    post,
+    path = "/create_strategy",
+    request_body = CreateStrategyRequest,
+    responses(
+        (status = 200, description = "Succeed", body = StrategyResponse),
+        (status = 400, description = "Invalid strategy tiers", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "新建命名策略"
+)]
+pub async fn create_strategy(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<CreateStrategyRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    validate_tiers(&payload.tiers).map_err(|e| (StatusCode::BAD_REQUEST, Json(e.into())))?;
+    let strategy = UserStrategy::new(&user_id, payload.name, payload.tiers);
+    let strategy = db_create_strategy(strategy).await.map_err(|e| {
+        eprintln!("Database query error: {:?}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(error_code::SERVER_ERROR.into()),
         )
     })?;
+    upsert_user_strategy(strategy.clone()).await;
 
     let res = strategy.into_common_response_data();
     Ok(Json(res))
@@ -121,36 +174,273 @@ pub async fn get_strategy(
 #[utoipa::path(
     post,
     path = "/update_strategy",
-    request_body = UpdateStrategy,
+    request_body = UpdateStrategyRequest,
     responses(
-        (status = 200, description = "Succeed", body = CommonResponse),
+        (status = 200, description = "Succeed", body = StrategyResponse),
+        (status = 400, description = "Strategy not found or invalid tiers", body = CommonError),
         (status = 500, description = "Internal server error", body = CommonError)
     ),
-    description = "更新用户当前策略"
+    description = "修改策略名称或档位，保存为新版本"
 )]
 pub async fn update_strategy(
     Extension(user_id): Extension<String>,
-    Json(payload): Json<UpdateStrategy>,
+    Json(payload): Json<UpdateStrategyRequest>,
 ) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
-    update_user_strategy(&user_id, payload.cfg.clone()).await;
-    db_update_strategy(&user_id, payload).await.map_err(|e| {
+    let mut strategy = get_user_strategy(&user_id, &payload.strategy_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(error_code::STRATEGY_NOT_FOUND.into()),
+            )
+        })?;
+    if let Some(name) = payload.name {
+        strategy.name = name;
+    }
+    if let Some(tiers) = payload.tiers {
+        validate_tiers(&tiers).map_err(|e| (StatusCode::BAD_REQUEST, Json(e.into())))?;
+        strategy.tiers = tiers;
+    }
+    strategy.next_version();
+
+    let strategy = db_update_strategy(strategy).await.map_err(|e| {
         eprintln!("Database query error: {:?}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(error_code::SERVER_ERROR.into()),
         )
     })?;
+    upsert_user_strategy(strategy.clone()).await;
+
+    let res = strategy.into_common_response_data();
+    Ok(Json(res))
+}
+
+#[utoipa::path(
+    post,
+    path = "/delete_strategy",
+    request_body = DeleteStrategyRequest,
+    responses(
+        (status = 200, description = "Succeed", body = CommonResponse),
+        (status = 400, description = "Strategy not found or invalid stop policy", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "删除策略，已开仓位保留各自的档位"
+)]
+pub async fn delete_strategy(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<DeleteStrategyRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    get_user_strategy(&user_id, &payload.strategy_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(error_code::STRATEGY_NOT_FOUND.into()),
+            )
+        })?;
+
+    db_delete_strategy(&payload.strategy_id)
+        .await
+        .map_err(|e| {
+            eprintln!("Database query error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        })?;
+    remove_user_strategy(&user_id, &payload.strategy_id).await;
 
     let res = CommonResponse::default();
     Ok(Json(res))
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_strategy_versions",
+    request_body = GetStrategyVersionsRequest,
+    responses(
+        (status = 200, description = "Succeed", body = GetStrategyVersionsResponse),
+        (status = 400, description = "Strategy not found or invalid stop policy", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "策略的历史版本，按版本号倒序"
+)]
+pub async fn get_strategy_versions(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<GetStrategyVersionsRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    get_user_strategy(&user_id, &payload.strategy_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(error_code::STRATEGY_NOT_FOUND.into()),
+            )
+        })?;
+
+    let versions = db_get_strategy_versions(&payload.strategy_id)
+        .await
+        .map_err(|e| {
+            eprintln!("Database query error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        })?;
+
+    let res = versions.into_common_response_data();
+    Ok(Json(res))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rollback_strategy",
+    request_body = RollbackStrategyRequest,
+    responses(
+        (status = 200, description = "Succeed", body = StrategyResponse),
+        (status = 400, description = "Strategy or version not found", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "回滚到历史版本，已开仓位不受影响"
+)]
+pub async fn rollback_strategy(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<RollbackStrategyRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let mut strategy = get_user_strategy(&user_id, &payload.strategy_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(error_code::STRATEGY_NOT_FOUND.into()),
+            )
+        })?;
+
+    let version = db_get_strategy_version(&payload.version_id)
+        .await
+        .map_err(|e| {
+            eprintln!("Database query error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        })?
+        .filter(|v| v.strategy_id == strategy.strategy_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(error_code::STRATEGY_VERSION_NOT_FOUND.into()),
+            )
+        })?;
+
+    strategy.name = version.name;
+    strategy.tiers = version.tiers;
+    strategy.next_version();
+    let strategy = db_update_strategy(strategy).await.map_err(|e| {
+        eprintln!("Database query error: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    })?;
+    upsert_user_strategy(strategy.clone()).await;
+
+    let res = strategy.into_common_response_data();
+    Ok(Json(res))
+}
+
+#[utoipa::path(
+    post,
+    path = "/simulate_strategy",
+    request_body = SimulateStrategyRequest,
+    responses(
+        (status = 200, description = "Succeed", body = SimulateStrategyResponse),
+        (status = 400, description = "Invalid strategy or price series", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "按给定价格序列模拟止损移动，不下单"
+)]
+pub async fn simulate_strategy(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<SimulateStrategyRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let invalid_prices = || {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(error_code::INVALID_PRICE_SERIES.into()),
+        )
+    };
+    if payload.prices.is_empty() || payload.prices.len() > MAX_SIMULATION_PRICES {
+        return Err(invalid_prices());
+    }
+    let prices = payload
+        .prices
+        .iter()
+        .map(|p| Decimal::try_from(*p).ok().filter(|p| *p > Decimal::ZERO))
+        .collect::<Option<Vec<Decimal>>>()
+        .ok_or_else(invalid_prices)?;
+    if payload.entry_price <= Decimal::ZERO || payload.leverage == 0 {
+        return Err(invalid_prices());
+    }
+    payload
+        .stop_policy
+        .validate(Decimal::from(payload.leverage))
+        .map_err(|code| (StatusCode::BAD_REQUEST, Json(code.into())))?;
+
+    let strategies = match (payload.strategies, payload.strategy_id) {
+        (Some(strategies), _) => {
+            validate_tiers(&strategies).map_err(|e| (StatusCode::BAD_REQUEST, Json(e.into())))?;
+            strategies
+        }
+        (None, Some(strategy_id)) => {
+            get_user_strategy(&user_id, &strategy_id)
+                .await
+                .ok_or_else(|| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(error_code::STRATEGY_NOT_FOUND.into()),
+                    )
+                })?
+                .tiers
+        }
+        (None, None) => Vec::new(),
+    };
+
+    let mut position = Position::simulated(
+        payload.direction,
+        payload.entry_price,
+        Decimal::from(payload.leverage),
+        payload.stop_loss_percent,
+        StopPolicyKind::from_config(payload.stop_policy, strategies),
+    );
+    let initial_stop_loss = position.stop_loss;
+    let steps = position.simulate(&prices, payload.interval_ms.unwrap_or(1000) as i64);
+
+    let exit = steps
+        .iter()
+        .position(|s| s.exit)
+        .map(|i| SimulationStepData::new(i, &steps[i]));
+    let data = SimulationData {
+        initial_stop_loss: initial_stop_loss.normalize().to_string(),
+        steps: steps
+            .iter()
+            .enumerate()
+            .map(|(i, s)| SimulationStepData::new(i, s))
+            .collect(),
+        exit,
+    };
+    let res = data.into_common_response_data();
+    Ok(Json(res))
+}
+
 #[utoipa::path(
     post,
     path = "/create_position",
     request_body = CreatePositionRequest,
     responses(
         (status = 200, description = "Succeed", body = CommonResponse),
+        (status = 400, description = "Strategy not found or invalid stop policy", body = CommonError),
         (status = 500, description = "Internal server error", body = CommonError)
     ),
     description = "开仓"
@@ -159,6 +449,11 @@ pub async fn create_position(
     Extension(user_id): Extension<String>,
     Json(payload): Json<CreatePositionRequest>,
 ) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    payload
+        .stop_policy
+        .validate(Decimal::from(payload.leverage))
+        .map_err(|code| (StatusCode::BAD_REQUEST, Json(code.into())))?;
+
     let price = get_symbol_price(&payload.symbol).await.map_err(|_e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -173,12 +468,12 @@ pub async fn create_position(
         )
     })?;
 
-    let strategy = get_user_spec_strategy(&user_id, payload.strategy_id)
+    let strategy = get_user_strategy(&user_id, &payload.strategy_id)
         .await
         .ok_or_else(|| {
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(error_code::SERVER_ERROR.into()),
+                StatusCode::BAD_REQUEST,
+                Json(error_code::STRATEGY_NOT_FOUND.into()),
             )
         })?;
 
@@ -186,40 +481,39 @@ pub async fn create_position(
         Direction::Long => ("BUY", "LONG", &price.buy),
         Direction::Short => ("SELL", "SHORT", &price.sell),
     };
-    let price_f64: f64 = price.parse().unwrap();
-    let quantity = calculate_quantity(&payload, price_f64, percision);
-
-    let secret_key = get_secret_key(&user_id).await.ok_or_else(|| {
+    let price: Decimal = price.parse().map_err(|_e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(error_code::SERVER_ERROR.into()),
+            Json(error_code::INVALIAD_SYMBOLE.into()),
         )
     })?;
+    let quantity = calculate_quantity(&payload, price, percision);
 
-    println!("secret_key: {:?}", secret_key);
-
-    let _ = change_leverage(
-        &payload.symbol,
-        payload.leverage as u32,
-        &secret_key.key,
-        &secret_key.secret,
-    )
-    .await
-    .map_err(|e| {
-        eprintln!("change_leverage error: {:?}", e);
+    let secret_key = get_secret_key(&user_id).await.ok_or_else(|| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(error_code::SERVER_ERROR.into()),
         )
     })?;
 
+    let exchange = exchange(&secret_key);
+    let _ = exchange
+        .change_leverage(&payload.symbol, payload.leverage as u32)
+        .await
+        .map_err(|e| {
+            eprintln!("change_leverage error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        })?;
+
     let order = create_position_order(
+        exchange.as_ref(),
         &payload.symbol,
         side,
         position_side,
         &quantity,
-        &secret_key.key,
-        &secret_key.secret,
     )
     .await
     .map_err(|e| {
@@ -230,22 +524,33 @@ pub async fn create_position(
         )
     })?;
 
-    let price_f64: f64 = order.avg_price.parse().unwrap();
-    let position = Position::new(
+    let entry_price: Decimal = order.avg_price.parse().map_err(|e| {
+        eprintln!("Invalid avg price {}: {:?}", order.avg_price, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    })?;
+    let stop_policy = StopPolicyKind::from_config(payload.stop_policy, strategy.tiers);
+    let mut position = Position::new(
         order.order_id,
         user_id.clone(),
         payload.symbol,
-        price_f64,
+        entry_price,
         payload.direction,
         quantity,
-        payload.leverage as f64,
+        Decimal::from(payload.leverage),
         payload.stop_loss_percent,
-        strategy,
-        secret_key.key,
-        secret_key.secret,
+        stop_policy,
     )
     .await;
+    position.account = secret_key;
+    position.strategy_version_id = Some(strategy.version_id);
+    position.exit_rules = payload.exit_rules;
+    position.update_break_even_price().await;
+    position.place_stop_order().await;
 
+    save_position(position.clone());
     inser_user_positon(position).await.map_err(|e| {
         eprintln!("inser_user_positon: {:?}", e);
         (
@@ -264,13 +569,22 @@ pub async fn create_position(
     request_body = ClosePositionRequest,
     responses(
         (status = 200, description = "Succeed", body = CommonResponse),
+        (status = 400, description = "Position not found", body = CommonError),
         (status = 500, description = "Internal server error", body = CommonError)
     ),
-    description = "平仓"
+    description = "平仓，传入 order_id 时只平掉该笔仓位"
 )]
 pub async fn close_position(
     Extension(user_id): Extension<String>,
     Json(payload): Json<ClosePositionRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    // 手动平仓降低风险，查询持仓和下单与止损、平仓一样不受限频排队影响
+    with_priority(Priority::High, close_user_position(user_id, payload)).await
+}
+
+async fn close_user_position(
+    user_id: String,
+    payload: ClosePositionRequest,
 ) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
     let (side, position_side) = match payload.direction {
         Direction::Long => ("SELL", "LONG"),
@@ -284,13 +598,41 @@ pub async fn close_position(
         )
     })?;
 
+    let exchange = exchange(&secret_key);
+    if let Some(order_id) = payload.order_id {
+        let position =
+            claim_user_position_exit(&payload.symbol, &user_id, &payload.direction, order_id)
+                .await
+                .map_err(position_error)?;
+        let r = close_position_quantity(
+            exchange.as_ref(),
+            &user_id,
+            &payload.symbol,
+            side,
+            position_side,
+            &position.quantity,
+        )
+        .await;
+        if let Err(e) = r {
+            eprintln!("Close position {} error: {:?}", order_id, e);
+            release_position_exit(&payload.symbol, order_id, position.status).await;
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            ));
+        }
+        remove_user_position(&payload.symbol, order_id).await;
+
+        let res = CommonResponse::default();
+        return Ok(Json(res));
+    }
+
     let _r = close_position_order(
+        exchange.as_ref(),
         &user_id,
         &payload.symbol,
         side,
         position_side,
-        &secret_key.key,
-        &secret_key.secret,
     )
     .await
     .map_err(|e| {
@@ -306,3 +648,100 @@ pub async fn close_position(
     let res = CommonResponse::default();
     Ok(Json(res))
 }
+
+#[utoipa::path(
+    post,
+    path = "/update_position",
+    request_body = UpdatePositionRequest,
+    responses(
+        (status = 200, description = "Succeed", body = UpdatePositionResponse),
+        (status = 400, description = "Invalid update", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "修改持仓的止损价、策略档位或止损方式，传入 order_id 时只修改该笔仓位"
+)]
+pub async fn update_position(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<UpdatePositionRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let price = get_symbol_price(&payload.symbol).await.map_err(|_e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::INVALIAD_SYMBOLE.into()),
+        )
+    })?;
+    // 做多按买一价、做空按卖一价校验止损
+    let market_price = match payload.direction {
+        Direction::Long => &price.sell,
+        Direction::Short => &price.buy,
+    };
+    let market_price: Decimal = market_price.parse().map_err(|_e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::INVALIAD_SYMBOLE.into()),
+        )
+    })?;
+
+    let (strategies, strategy_version_id) = match (payload.strategies, payload.strategy_id) {
+        (Some(strategies), _) => {
+            validate_tiers(&strategies).map_err(|e| (StatusCode::BAD_REQUEST, Json(e.into())))?;
+            (Some(strategies), None)
+        }
+        (None, Some(strategy_id)) => {
+            let strategy = get_user_strategy(&user_id, &strategy_id)
+                .await
+                .ok_or_else(|| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(error_code::STRATEGY_NOT_FOUND.into()),
+                    )
+                })?;
+            (Some(strategy.tiers), Some(strategy.version_id))
+        }
+        (None, None) => (None, None),
+    };
+
+    let update = PositionUpdate {
+        stop_loss: payload.stop_loss,
+        force: payload.force,
+        strategies,
+        strategy_version_id,
+        stop_policy: payload.stop_policy,
+        exit_rules: payload.exit_rules,
+    };
+    let positions = update_user_positions(
+        &payload.symbol,
+        &user_id,
+        &payload.direction,
+        payload.order_id,
+        update,
+        market_price,
+    )
+    .await
+    .map_err(position_error)?;
+
+    let data: Vec<PositionData> = positions.iter().map(PositionData::from).collect();
+    let res = data.into_common_response_data();
+    Ok(Json(res))
+}
+
+// 仓位操作的校验错误返回对应错误码，其他错误按服务器错误处理
+fn position_error(e: Error) -> (StatusCode, Json<CommonError>) {
+    match e {
+        Error::ErrorCode(code) => (
+            StatusCode::BAD_REQUEST,
+            Json(
+                error_code::error_phrase(code)
+                    .unwrap_or(error_code::SERVER_ERROR)
+                    .into(),
+            ),
+        ),
+        e => {
+            eprintln!("position error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        }
+    }
+}
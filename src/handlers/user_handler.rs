@@ -2,18 +2,23 @@ use axum::{http::StatusCode, Extension, Json};
 use rust_decimal::Decimal;
 
 use crate::{
+    biance::{paper, signed::ApiKey},
     database::{
-        strategy_db::{db_create_strategy, db_get_strategy},
-        user_db::{db_create_user, db_get_user_info},
+        strategy_db::{db_create_strategy, db_get_user_strategies},
+        user_db::{db_create_user, db_get_user_info, db_update_paper_trading},
     },
     error::error_code,
     models::{
-        user_model::{CreateUserInput, CreateUserRequest, UserResponse},
+        user_model::{
+            CreateUserInput, CreateUserRequest, PaperAccountData, PaperAccountResponse,
+            UpdatePaperTradingRequest, UserResponse,
+        },
         CommonError, CommonResponse, IntoCommonResponse,
     },
     static_items::{
+        position::get_all_positions,
         secret_key::{delete_secret_key, insert_secret_key, SecretKey},
-        strategy::{delete_user_strategy, insert_user_strategy},
+        strategy::{default_tiers, delete_user_strategy, insert_user_strategies, UserStrategy},
         user_info::{delete_user_info, get_agent_id, insert_user_info, UserInfo},
     },
 };
@@ -32,11 +37,20 @@ pub async fn create_user(
     Extension(user_id): Extension<String>,
     Json(payload): Json<CreateUserRequest>,
 ) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    if !ApiKey::new(&payload.key, &payload.secret, payload.key_type).is_valid() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(error_code::INVALID_API_SECRET.into()),
+        ));
+    }
+
     let input = CreateUserInput {
         user_id: user_id.clone(),
         agent_id: payload.agent_id,
         key: payload.key,
         secret: payload.secret,
+        key_type: payload.key_type,
+        paper_trading: payload.paper_trading,
     };
 
     let data = db_create_user(input).await.map_err(|e| {
@@ -47,7 +61,8 @@ pub async fn create_user(
         )
     })?;
 
-    db_create_strategy(&user_id).await.map_err(|e| {
+    let strategy = UserStrategy::new(&user_id, "default".to_owned(), default_tiers());
+    db_create_strategy(strategy).await.map_err(|e| {
         eprintln!("Database query error: {:?}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -79,8 +94,7 @@ pub async fn get_user_info(
         )
     })?;
 
-    let key = SecretKey::new(data.user_id.clone(), data.key.clone(), data.secret.clone());
-    insert_secret_key(key).await;
+    insert_secret_key(SecretKey::from(&data)).await;
 
     let user_info = UserInfo::new(
         data.user_id.clone(),
@@ -89,14 +103,14 @@ pub async fn get_user_info(
     );
     insert_user_info(user_info).await;
 
-    let strategy = db_get_strategy(&user_id).await.map_err(|e| {
+    let strategies = db_get_user_strategies(&user_id).await.map_err(|e| {
         eprintln!("Database query error: {:?}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(error_code::SERVER_ERROR.into()),
         )
     })?;
-    insert_user_strategy(strategy).await;
+    insert_user_strategies(&user_id, strategies).await;
 
     let a = get_agent_id(&user_id).await;
     println!("agent_id: {:?}", a);
@@ -123,3 +137,99 @@ pub async fn logout(
     let res = CommonResponse::default();
     Ok(Json(res))
 }
+
+#[utoipa::path(
+    post,
+    path = "/update_paper_trading",
+    request_body = UpdatePaperTradingRequest,
+    responses(
+        (status = 200, description = "Succeed", body = UserResponse),
+        (status = 400, description = "Positions still open", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "切换纸面交易模式，需要先平掉全部仓位"
+)]
+pub async fn update_paper_trading(
+    Extension(user_id): Extension<String>,
+    Json(payload): Json<UpdatePaperTradingRequest>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    // 已有仓位记录了下单时的账户类型，切换后无法再由原账户平仓
+    let positions = get_all_positions().await;
+    if positions.iter().any(|p| p.user_id == user_id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(error_code::POSITIONS_STILL_OPEN.into()),
+        ));
+    }
+
+    let data = db_update_paper_trading(&user_id, payload.paper_trading)
+        .await
+        .map_err(|e| {
+            eprintln!("Database query error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::USER_NOT_FOUND.into()),
+            )
+        })?;
+    if payload.reset {
+        paper::reset_account(&user_id).await.map_err(|e| {
+            eprintln!("Database query error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(error_code::SERVER_ERROR.into()),
+            )
+        })?;
+    }
+    insert_secret_key(SecretKey::from(&data)).await;
+
+    let res = data.into_common_response_data();
+    Ok(Json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_paper_account",
+    responses(
+        (status = 200, description = "Succeed", body = PaperAccountResponse),
+        (status = 400, description = "Paper trading is not enabled", body = CommonError),
+        (status = 500, description = "Internal server error", body = CommonError)
+    ),
+    description = "纸面交易账户的余额、已实现盈亏和持仓"
+)]
+pub async fn get_paper_account(
+    Extension(user_id): Extension<String>,
+) -> Result<Json<CommonResponse>, (StatusCode, Json<CommonError>)> {
+    let data = db_get_user_info(&user_id).await.map_err(|e| {
+        eprintln!("Database query error: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::USER_NOT_FOUND.into()),
+        )
+    })?;
+    if !data.paper_trading {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(error_code::PAPER_TRADING_DISABLED.into()),
+        ));
+    }
+
+    let server_error = |e| {
+        eprintln!("Paper account error: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(error_code::SERVER_ERROR.into()),
+        )
+    };
+    let account = paper::get_account(&user_id).await.map_err(server_error)?;
+    let positions = paper::get_risk(&user_id).await.map_err(server_error)?;
+    let data = PaperAccountData {
+        balance: account.balance.normalize().to_string(),
+        available: account.available().normalize().to_string(),
+        realized_pnl: account.realized_pnl.normalize().to_string(),
+        fees: account.fees.normalize().to_string(),
+        positions,
+    };
+
+    let res = data.into_common_response_data();
+    Ok(Json(res))
+}
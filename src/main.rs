@@ -1,3 +1,5 @@
+mod alert;
+mod backtest;
 mod biance;
 mod database;
 mod error;
@@ -5,31 +7,44 @@ mod handlers;
 mod models;
 mod routes;
 mod static_items;
+mod tasks;
 mod utils;
 mod websocket;
 
 use std::sync::Arc;
 
-use database::create_tables;
+use biance::config::init_exchange_config;
+use database::{create_tables, position_db::start_position_writer};
 use dotenvy::dotenv;
 use service_utils_rs::{
     services::{db::init_db, http::http_server, jwt::Jwt},
     settings::Settings,
 };
-use static_items::percision::init_percisions;
+use static_items::{percision::init_percisions, position::restore_positions};
+use tasks::{
+    executor::start_executor, exit_timer::start_exit_timer, reconciler::start_reconciler,
+    time_sync::start_time_sync,
+};
 use websocket::connection::start_websocket;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     let settings = Settings::new("config/services.toml").unwrap();
+    init_exchange_config("config/services.toml").unwrap();
     init_db(settings.surrealdb).await.unwrap();
     create_tables().await.unwrap();
     init_percisions().await;
+    tokio::spawn(start_position_writer());
+    tokio::spawn(start_executor());
+    tokio::spawn(start_time_sync());
+    restore_positions().await.unwrap();
 
     let jwt = Arc::new(Jwt::new(settings.jwt));
     let router = routes::create_routes(jwt);
     let http_task = http_server::start(settings.http.port, router);
     let ws_task = start_websocket();
-    let _ = tokio::join!(ws_task, http_task);
+    let reconcile_task = start_reconciler();
+    let exit_timer_task = start_exit_timer();
+    let _ = tokio::join!(ws_task, http_task, reconcile_task, exit_timer_task);
 }
@@ -0,0 +1,98 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    backtest::{
+        data::{DataKind, Gap},
+        engine::{BacktestReport, Signal},
+        market::{BookTicker, Kline},
+        optimizer::{Objective, OptimizeReport, SearchMode, SearchSpace},
+    },
+    static_items::{position::ExitRules, stop_policy::StopPolicyConfig, strategy::Strategy},
+};
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct RunBacktestRequest {
+    pub klines: Option<Vec<Kline>>, // K 线和 bookTicker 二选一，优先使用 bookTicker
+    pub book_tickers: Option<Vec<BookTicker>>,
+    pub symbol: Option<String>, // 未直接传入行情时使用该交易对已导入的数据
+    pub data_kind: Option<DataKind>, // 已导入数据的类型，默认依次使用 bookTicker、aggTrades、K 线
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub signals: Vec<Signal>,
+    pub strategy_id: Option<String>,       // 使用用户保存的策略
+    pub strategies: Option<Vec<Strategy>>, // 直接指定档位，优先于 strategy_id
+    #[serde(default)]
+    pub stop_policy: StopPolicyConfig,
+    #[serde(default)]
+    pub exit_rules: ExitRules,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub initial_capital: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub taker_fee_rate: Decimal,
+    #[serde(default, with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub slippage: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunBacktestResponse {
+    pub code: u16,
+    pub data: BacktestReport,
+    pub message: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct ImportDataRequest {
+    pub file_name: String, // 数据目录 raw 子目录下的 Binance CSV 文件名
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportDataResult {
+    pub symbol: String,
+    pub kind: DataKind,
+    pub count: usize, // 本次导入的价格点数量
+    pub start_time: i64,
+    pub end_time: i64,
+    pub gaps: Vec<Gap>, // 本次导入数据中的缺失区间，与已保存的缺失区间合并保存
+    pub stored: usize,  // 合并后该交易对保存的价格点数量
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportDataResponse {
+    pub code: u16,
+    pub data: ImportDataResult,
+    pub message: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct OptimizeRequest {
+    #[serde(flatten)]
+    pub backtest: RunBacktestRequest, // 信号中的杠杆和止损比例会被搜索的参数替换
+    pub space: SearchSpace,
+    pub mode: SearchMode,
+    #[serde(default)]
+    pub objective: Objective,
+    #[serde(default = "default_folds")]
+    pub folds: usize,
+    #[serde(default = "default_top")]
+    pub top: usize,
+}
+
+fn default_folds() -> usize {
+    3
+}
+
+fn default_top() -> usize {
+    10
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OptimizeResponse {
+    pub code: u16,
+    pub data: OptimizeReport,
+    pub message: String,
+}
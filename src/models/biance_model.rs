@@ -2,7 +2,7 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use utoipa::{PartialSchema, ToSchema};
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
 pub struct Risk {
     pub symbol: String,
 
@@ -79,25 +79,25 @@ pub struct Risk {
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct TradeRecord {
-    buyer: bool,        // 是否是买方
-    commission: String, // 手续费
+    pub buyer: bool,        // 是否是买方
+    pub commission: String, // 手续费
     #[serde(rename = "commissionAsset")]
-    commission_asset: String, // 手续费计价单位
-    id: u64,            // 交易ID
-    maker: bool,        // 是否是挂单方
+    pub commission_asset: String, // 手续费计价单位
+    pub id: u64,            // 交易ID
+    pub maker: bool,        // 是否是挂单方
     #[serde(rename = "orderId")]
-    order_id: u64, // 订单编号
-    price: String,      // 成交价
-    qty: String,        // 成交量
+    pub order_id: u64, // 订单编号
+    pub price: String,      // 成交价
+    pub qty: String,        // 成交量
     #[serde(rename = "quoteQty")]
-    quote_qty: String, // 成交额
+    pub quote_qty: String, // 成交额
     #[serde(rename = "realizedPnl")]
     pub realized_pnl: String, // 实现盈亏
-    side: String,       // 买卖方向
+    pub side: String,       // 买卖方向
     #[serde(rename = "positionSide")]
-    position_side: String, // 持仓方向
-    symbol: String,     // 交易对
-    time: u64,          // 时间
+    pub position_side: String, // 持仓方向
+    pub symbol: String,     // 交易对
+    pub time: u64,          // 时间
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -108,6 +108,8 @@ pub struct BiannceOrder {
     pub avg_price: String,
     #[serde(rename = "executedQty")]
     pub executed_qty: String,
+    #[serde(default)]
+    pub status: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -115,27 +117,27 @@ pub struct ActiveOrder {
     #[serde(rename = "orderId")]
     pub order_id: u64,
     #[serde(rename = "origQty")]
-    orig_qty: String,
-    price: String,
+    pub orig_qty: String,
+    pub price: String,
     #[serde(rename = "reduceOnly")]
-    reduce_only: bool,
-    side: String,
+    pub reduce_only: bool,
+    pub side: String,
     #[serde(rename = "positionSide")]
-    position_side: String,
-    status: String,
+    pub position_side: String,
+    pub status: String,
     #[serde(rename = "stopPrice")]
-    stop_price: String,
-    symbol: String,
+    pub stop_price: String,
+    pub symbol: String,
     #[serde(rename = "timeInForce")]
-    time_in_force: String,
+    pub time_in_force: String,
     #[serde(rename = "type")]
-    order_type: String,
+    pub order_type: String,
     #[serde(rename = "origType")]
-    orig_type: String,
+    pub orig_type: String,
     #[serde(rename = "updateTime")]
-    update_time: i64,
+    pub update_time: i64,
     #[serde(rename = "workingType")]
-    working_type: String,
+    pub working_type: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -156,4 +158,40 @@ pub struct SymbolInfo {
     pub symbol: String,
     #[serde(rename = "quantityPrecision")]
     pub quantity_precision: u8,
+    pub filters: Vec<SymbolFilter>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "filterType")]
+pub enum SymbolFilter {
+    #[serde(rename = "PRICE_FILTER")]
+    PriceFilter {
+        #[serde(rename = "tickSize")]
+        tick_size: Decimal, // 价格最小变动单位
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl SymbolInfo {
+    pub fn tick_size(&self) -> Option<Decimal> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::PriceFilter { tick_size } => Some(*tick_size),
+            SymbolFilter::Other => None,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PremiumIndex {
+    pub symbol: String,
+    #[serde(rename = "nextFundingTime")]
+    pub next_funding_time: i64, // 下一次资金费结算时间（毫秒）
+}
+
+// 开仓和止损平仓都是市价单，只需要吃单手续费率
+#[derive(Deserialize, Debug)]
+pub struct CommissionRate {
+    #[serde(rename = "takerCommissionRate")]
+    pub taker_commission_rate: Decimal, // 吃单手续费率
 }
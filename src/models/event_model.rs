@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionEventKind {
+    ReconcileDropped,  // 交易所已无持仓，移除本地仓位
+    ReconcileAdjusted, // 交易所持仓减少，调整本地数量
+    ExitFailed,        // 止损平仓重试耗尽仍未成功
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreatePositionEventRequest {
+    pub user_id: String,
+    pub order_id: u64,
+    pub symbol: String,
+    pub kind: PositionEventKind,
+    pub detail: String,
+}
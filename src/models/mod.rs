@@ -1,5 +1,7 @@
 pub mod auth_model;
+pub mod backtest_model;
 pub mod biance_model;
+pub mod event_model;
 pub mod fee_model;
 pub mod record_model;
 pub mod trade_model;
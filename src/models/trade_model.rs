@@ -1,8 +1,13 @@
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use utoipa::{PartialSchema, ToSchema};
 
-use crate::static_items::{position::Direction, strategy::StrategyConfig};
+use crate::static_items::{
+    position::{Direction, ExitRules, Position, PositionStatus, SimulationStep},
+    stop_policy::StopPolicyConfig,
+    strategy::{Strategy, StrategyVersion, UserStrategy},
+};
 
 #[derive(Serialize, ToSchema, Debug)]
 pub struct RiskData {
@@ -12,6 +17,7 @@ pub struct RiskData {
     pub entry_price: String,
     pub stop_price: String,
     pub quantity: String,
+    pub status: Option<PositionStatus>, // 托管仓位的状态，未托管时为空
     #[schema(schema_with = String::schema)]
     pub update_time: DateTime<Utc>,
 }
@@ -26,13 +32,51 @@ pub struct GetRiskResponse {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct GetStategyResponse {
     pub code: u16,
-    pub data: StrategyConfig,
+    pub data: Vec<UserStrategy>,
     pub message: String,
 }
 
-#[derive(Deserialize, Serialize, ToSchema, Debug)]
-pub struct UpdateStrategy {
-    pub cfg: StrategyConfig,
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StrategyResponse {
+    pub code: u16,
+    pub data: UserStrategy,
+    pub message: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct CreateStrategyRequest {
+    pub name: String,
+    pub tiers: Vec<Strategy>,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct UpdateStrategyRequest {
+    pub strategy_id: String,
+    pub name: Option<String>,
+    pub tiers: Option<Vec<Strategy>>,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct DeleteStrategyRequest {
+    pub strategy_id: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct GetStrategyVersionsRequest {
+    pub strategy_id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GetStrategyVersionsResponse {
+    pub code: u16,
+    pub data: Vec<StrategyVersion>,
+    pub message: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct RollbackStrategyRequest {
+    pub strategy_id: String,
+    pub version_id: String, // 回滚到该版本的名称和档位，作为新版本保存
 }
 
 #[derive(Deserialize, ToSchema, Debug)]
@@ -40,13 +84,217 @@ pub struct CreatePositionRequest {
     pub symbol: String,
     pub direction: Direction,
     pub leverage: u8,
-    pub margin: f64,
-    pub stop_loss_percent: f64,
-    pub strategy_id: u8,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub margin: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub stop_loss_percent: Decimal,
+    pub strategy_id: String,
+    #[serde(default)]
+    pub stop_policy: StopPolicyConfig,
+    #[serde(default)]
+    pub exit_rules: ExitRules,
 }
 
 #[derive(Deserialize, ToSchema, Debug)]
 pub struct ClosePositionRequest {
     pub symbol: String,
     pub direction: Direction,
+    pub order_id: Option<u64>, // 只平掉该笔仓位，为空时平掉该方向的全部仓位
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct UpdatePositionRequest {
+    pub symbol: String,
+    pub direction: Direction,
+    pub order_id: Option<u64>, // 只修改该笔仓位，为空时修改该方向的全部仓位
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    #[schema(value_type = Option<f64>)]
+    pub stop_loss: Option<Decimal>,
+    #[serde(default)]
+    pub force: bool, // 为 true 时允许放宽止损
+    pub strategy_id: Option<String>, // 使用用户保存的策略替换剩余档位
+    pub strategies: Option<Vec<Strategy>>, // 直接指定剩余档位，优先于 strategy_id
+    pub stop_policy: Option<StopPolicyConfig>,
+    pub exit_rules: Option<ExitRules>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PositionData {
+    pub order_id: u64,
+    pub symbol: String,
+    pub direction: Direction,
+    pub status: PositionStatus,
+    pub entry_price: String,
+    pub stop_loss: String,
+    pub break_even_price: String, // 计入手续费的保本价，锁定利润的止损不会低于该价格
+    pub highest_price: String,
+    pub lowest_price: String,
+    pub quantity: String,
+    pub stop_policy: StopPolicyConfig,
+    pub strategies: Vec<Strategy>, // 阶梯止损尚未触发的档位
+    pub strategy_version_id: Option<String>,
+    pub exit_rules: ExitRules,
+    pub opened_at: i64,
+}
+
+impl From<&Position> for PositionData {
+    fn from(position: &Position) -> Self {
+        PositionData {
+            order_id: position.order_id,
+            symbol: position.symbol.clone(),
+            direction: position.direction.clone(),
+            status: position.status,
+            entry_price: position.entry_price.to_string(),
+            stop_loss: position.stop_loss.to_string(),
+            break_even_price: position.break_even_price.to_string(),
+            highest_price: position.highest_price.to_string(),
+            lowest_price: position.lowest_price.to_string(),
+            quantity: position.quantity.clone(),
+            stop_policy: position.stop_policy.config(),
+            strategies: position.stop_policy.remaining_strategies(),
+            strategy_version_id: position.strategy_version_id.clone(),
+            exit_rules: position.exit_rules.clone(),
+            opened_at: position.opened_at,
+        }
+    }
+}
+
+// 同一用户、交易对、方向下多笔仓位的汇总
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PositionGroupData {
+    pub symbol: String,
+    pub direction: Direction,
+    pub status: PositionStatus, // 任一仓位平仓失败或正在平仓时显示对应状态
+    pub quantity: String,       // 总数量
+    pub entry_price: String,    // 按数量加权的平均入场价
+    pub stop_loss: String,      // 最先触发的止损价
+    pub lots: Vec<PositionData>,
+}
+
+impl PositionGroupData {
+    // 按交易对和方向汇总仓位
+    pub fn group(positions: &[Position]) -> Vec<Self> {
+        let mut groups: Vec<PositionGroupData> = Vec::new();
+        let mut lots: Vec<Vec<&Position>> = Vec::new();
+        for position in positions {
+            let index = groups
+                .iter()
+                .position(|g| g.symbol == position.symbol && g.direction == position.direction);
+            match index {
+                Some(index) => lots[index].push(position),
+                None => {
+                    groups.push(PositionGroupData {
+                        symbol: position.symbol.clone(),
+                        direction: position.direction.clone(),
+                        status: PositionStatus::Open,
+                        quantity: String::new(),
+                        entry_price: String::new(),
+                        stop_loss: String::new(),
+                        lots: Vec::new(),
+                    });
+                    lots.push(vec![position]);
+                }
+            }
+        }
+
+        for (group, lots) in groups.iter_mut().zip(lots) {
+            let mut quantity = Decimal::ZERO;
+            let mut cost = Decimal::ZERO;
+            let mut stop_loss: Option<Decimal> = None;
+            for lot in lots.iter() {
+                let lot_quantity: Decimal = lot.quantity.parse().unwrap_or(Decimal::ZERO);
+                quantity += lot_quantity;
+                cost += lot_quantity * lot.entry_price;
+                // 做多时止损价越高越先触发，做空相反
+                stop_loss = Some(match (stop_loss, &lot.direction) {
+                    (None, _) => lot.stop_loss,
+                    (Some(stop), Direction::Long) => stop.max(lot.stop_loss),
+                    (Some(stop), Direction::Short) => stop.min(lot.stop_loss),
+                });
+                if lot.status == PositionStatus::ExitFailed
+                    || (lot.status == PositionStatus::ExitPending
+                        && group.status == PositionStatus::Open)
+                {
+                    group.status = lot.status;
+                }
+            }
+            let entry_price = if quantity.is_zero() {
+                Decimal::ZERO
+            } else {
+                cost / quantity
+            };
+            group.quantity = quantity.normalize().to_string();
+            group.entry_price = entry_price.round_dp(8).normalize().to_string();
+            group.stop_loss = stop_loss.unwrap_or_default().to_string();
+            group.lots = lots.into_iter().map(PositionData::from).collect();
+        }
+        groups
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GetPositionGroupsResponse {
+    pub code: u16,
+    pub data: Vec<PositionGroupData>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpdatePositionResponse {
+    pub code: u16,
+    pub data: Vec<PositionData>,
+    pub message: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct SimulateStrategyRequest {
+    pub direction: Direction,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub entry_price: Decimal,
+    pub leverage: u8,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub stop_loss_percent: Decimal,
+    pub strategy_id: Option<String>,       // 使用用户保存的策略
+    pub strategies: Option<Vec<Strategy>>, // 直接指定档位，优先于 strategy_id
+    #[serde(default)]
+    pub stop_policy: StopPolicyConfig,
+    pub prices: Vec<f64>,         // 按时间顺序的成交价格
+    pub interval_ms: Option<u64>, // 相邻价格的时间间隔，默认 1000
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulationStepData {
+    pub index: usize,
+    pub price: String,
+    pub stop_loss: String,
+    pub remaining: String, // 减仓后剩余的仓位比例
+}
+
+impl SimulationStepData {
+    pub fn new(index: usize, step: &SimulationStep) -> Self {
+        SimulationStepData {
+            index,
+            price: step.price.normalize().to_string(),
+            stop_loss: step.stop_loss.normalize().to_string(),
+            remaining: step.remaining.normalize().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulationData {
+    pub initial_stop_loss: String,
+    pub steps: Vec<SimulationStepData>,
+    pub exit: Option<SimulationStepData>, // 触发止损的价格点，未触发时为空
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulateStrategyResponse {
+    pub code: u16,
+    pub data: SimulationData,
+    pub message: String,
 }
@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::{PartialSchema, ToSchema};
+
+use crate::{biance::signed::KeyType, models::biance_model::Risk};
 // use utoipa::ToSchema;
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
@@ -10,6 +12,10 @@ pub struct User {
     pub balance: String,
     pub key: String,
     pub secret: String,
+    #[serde(default)]
+    pub key_type: KeyType,
+    #[serde(default)]
+    pub paper_trading: bool,
     #[schema(schema_with = String::schema)]
     pub created_at: DateTime<Utc>,
     #[schema(schema_with = String::schema)]
@@ -21,6 +27,10 @@ pub struct CreateUserRequest {
     pub agent_id: String,
     pub key: String,
     pub secret: String,
+    #[serde(default)]
+    pub key_type: KeyType, // secret 的类型，Ed25519 和 RSA 为 PEM 格式的私钥
+    #[serde(default)]
+    pub paper_trading: bool, // 为 true 时订单发往模拟交易所
 }
 
 #[derive(Serialize, ToSchema)]
@@ -29,6 +39,8 @@ pub struct CreateUserInput {
     pub agent_id: String,
     pub key: String,
     pub secret: String,
+    pub key_type: KeyType,
+    pub paper_trading: bool,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -42,3 +54,26 @@ pub struct UserResponse {
 pub struct UpdateUserInfoRequest {
     pub balance: f64,
 }
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct UpdatePaperTradingRequest {
+    pub paper_trading: bool,
+    #[serde(default)]
+    pub reset: bool, // 为 true 时模拟账户恢复初始余额并清空持仓
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PaperAccountData {
+    pub balance: String,   // 钱包余额
+    pub available: String, // 扣除持仓保证金后的可用余额
+    pub realized_pnl: String,
+    pub fees: String,
+    pub positions: Vec<Risk>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PaperAccountResponse {
+    pub code: u16,
+    pub data: PaperAccountData,
+    pub message: String,
+}
@@ -0,0 +1,18 @@
+use crate::handlers::backtest_handler::{import, optimize, run};
+use axum::{routing::post, Router};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    crate::handlers::backtest_handler::run,
+    crate::handlers::backtest_handler::optimize,
+    crate::handlers::backtest_handler::import,
+))]
+pub struct BacktestApi;
+
+pub fn routes_backtest() -> Router {
+    Router::new()
+        .route("/run", post(run))
+        .route("/optimize", post(optimize))
+        .route("/import", post(import))
+}
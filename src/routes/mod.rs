@@ -1,4 +1,5 @@
 mod auth_route;
+mod backtest_route;
 mod fee_route;
 mod record_route;
 mod trade_route;
@@ -6,6 +7,7 @@ mod user_route;
 
 use auth_route::{routes_auth, AuthApi};
 use axum::{middleware, Extension, Router};
+use backtest_route::{routes_backtest, BacktestApi};
 use fee_route::routes_fee;
 use record_route::{routes_record, RecordApi};
 use service_utils_rs::services::{
@@ -24,7 +26,8 @@ use utoipa_swagger_ui::SwaggerUi;
             (path = "/auth", api = AuthApi),
             (path = "/user", api = UserApi),
             (path = "/trade", api = TradeApi),
-            (path = "/record", api = RecordApi)
+            (path = "/record", api = RecordApi),
+            (path = "/backtest", api = BacktestApi)
         ),
     )]
 struct ApiDoc;
@@ -37,6 +40,7 @@ pub fn create_routes(jwt: Arc<Jwt>) -> Router {
         .nest("/fee", routes_fee())
         .nest("/trade", routes_trade())
         .nest("/record", routes_record())
+        .nest("/backtest", routes_backtest())
         .route_layer(middleware::from_fn(auth))
         .nest("/auth", routes_auth())
         .layer(Extension(jwt))
@@ -5,16 +5,23 @@ use axum::{
 use utoipa::OpenApi;
 
 use crate::handlers::trade_handler::{
-    close_position, create_position, get_risk, get_strategy, update_strategy,
+    close_position, create_position, create_strategy, delete_strategy, get_risk, get_strategy,
+    get_strategy_versions, rollback_strategy, simulate_strategy, update_position, update_strategy,
 };
 
 #[derive(OpenApi)]
 #[openapi(paths(
     crate::handlers::trade_handler::get_risk,
     crate::handlers::trade_handler::get_strategy,
+    crate::handlers::trade_handler::create_strategy,
     crate::handlers::trade_handler::update_strategy,
+    crate::handlers::trade_handler::delete_strategy,
+    crate::handlers::trade_handler::get_strategy_versions,
+    crate::handlers::trade_handler::rollback_strategy,
+    crate::handlers::trade_handler::simulate_strategy,
     crate::handlers::trade_handler::create_position,
     crate::handlers::trade_handler::close_position,
+    crate::handlers::trade_handler::update_position,
 ))]
 pub struct TradeApi;
 
@@ -22,7 +29,13 @@ pub fn routes_trade() -> Router {
     Router::new()
         .route("/get_risk", get(get_risk))
         .route("/get_strategy", get(get_strategy))
+        .route("/create_strategy", post(create_strategy))
         .route("/update_strategy", post(update_strategy))
+        .route("/delete_strategy", post(delete_strategy))
+        .route("/get_strategy_versions", post(get_strategy_versions))
+        .route("/rollback_strategy", post(rollback_strategy))
+        .route("/simulate_strategy", post(simulate_strategy))
         .route("/create_position", post(create_position))
         .route("/close_position", post(close_position))
+        .route("/update_position", post(update_position))
 }
@@ -4,7 +4,9 @@ use axum::{
 };
 use utoipa::OpenApi;
 
-use crate::handlers::user_handler::{create_user, get_user_info, logout};
+use crate::handlers::user_handler::{
+    create_user, get_paper_account, get_user_info, logout, update_paper_trading,
+};
 
 #[derive(OpenApi)]
 #[openapi(
@@ -12,6 +14,8 @@ use crate::handlers::user_handler::{create_user, get_user_info, logout};
         crate::handlers::user_handler::create_user,
         crate::handlers::user_handler::get_user_info,
         crate::handlers::user_handler::logout,
+        crate::handlers::user_handler::update_paper_trading,
+        crate::handlers::user_handler::get_paper_account,
     ),
     // components(schemas(ApiKeyAuth))
 )]
@@ -22,4 +26,6 @@ pub fn routes_user() -> Router {
         .route("/create", post(create_user))
         .route("/get_info", get(get_user_info))
         .route("/logout", post(logout))
+        .route("/update_paper_trading", post(update_paper_trading))
+        .route("/get_paper_account", get(get_paper_account))
 }
@@ -2,6 +2,7 @@ pub mod percision;
 pub mod position;
 pub mod price;
 pub mod secret_key;
+pub mod stop_policy;
 pub mod strategy;
 pub mod symbol;
 pub mod user_info;
@@ -3,16 +3,21 @@ use std::{
     sync::{Arc, LazyLock},
 };
 
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use tokio::sync::Mutex;
 
-use crate::biance::leverage::get_quantity_precision;
+use crate::biance::{
+    exchange::{BinanceExchange, Exchange},
+    SYSTEM_KEY,
+};
 
 use super::symbol;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Percision {
     pub value: u8,
+    pub tick_size: Decimal,
 }
 
 static PERCISION: LazyLock<Arc<PercisionsManager>> = LazyLock::new(PercisionsManager::new);
@@ -32,7 +37,8 @@ impl PercisionsManager {
 
     async fn init_percisions(&self) {
         let symbols = symbol::get_symbols();
-        let response = get_quantity_precision().await.unwrap();
+        let exchange = BinanceExchange::new(SYSTEM_KEY.clone());
+        let response = exchange.exchange_info().await.unwrap();
         for symbol in symbols {
             let symbol_uppercase = symbol.to_uppercase();
             if let Some(symbol_info) = response
@@ -44,6 +50,7 @@ impl PercisionsManager {
                     symbol.to_string(),
                     Percision {
                         value: symbol_info.quantity_precision,
+                        tick_size: symbol_info.tick_size().unwrap_or(Decimal::ZERO),
                     },
                 );
             }
@@ -58,6 +65,11 @@ impl PercisionsManager {
             None => None,
         }
     }
+
+    async fn get_symbol_tick_size(&self, symbol: &str) -> Option<Decimal> {
+        let map = self.keys.lock().await;
+        map.get(symbol).map(|key| key.tick_size)
+    }
 }
 
 fn get_percisions_manager() -> Arc<PercisionsManager> {
@@ -71,3 +83,7 @@ pub async fn init_percisions() {
 pub async fn get_symbol_percision(symbol: &str) -> Option<u8> {
     get_percisions_manager().get_symbol_percision(symbol).await
 }
+
+pub async fn get_symbol_tick_size(symbol: &str) -> Option<Decimal> {
+    get_percisions_manager().get_symbol_tick_size(symbol).await
+}
@@ -1,6 +1,20 @@
+use chrono::Utc;
+use rust_decimal::{Decimal, RoundingStrategy};
+
 use crate::{
-    error::{Error, Result},
-    utils::create_position_order,
+    alert::send_alert,
+    biance::{
+        exchange::{exchange, Exchange, OrderRequest},
+        rate_limit::{with_priority, Priority},
+    },
+    database::{
+        event_db::db_create_position_event,
+        position_db::{db_get_open_positions, save_position},
+    },
+    error::{error_code, Error, Result},
+    models::event_model::{CreatePositionEventRequest, PositionEventKind},
+    tasks::executor::{submit_job, ExecutionJob},
+    utils::{cancel_stop_order, create_position_order, create_stop_order, round_to_tick},
 };
 use serde::{Deserialize, Serialize};
 use std::{
@@ -8,11 +22,21 @@ use std::{
     fmt,
     str::FromStr,
     sync::{Arc, LazyLock},
+    time::Instant,
+};
+use tokio::{
+    sync::Mutex,
+    time::{sleep, Duration},
 };
-use tokio::sync::Mutex;
 use utoipa::ToSchema;
 
-use super::{strategy::Strategy, symbol::get_symbols};
+use super::{
+    percision::{get_symbol_percision, get_symbol_tick_size},
+    secret_key::{load_secret_key, SecretKey},
+    stop_policy::{StopContext, StopPolicy, StopPolicyConfig, StopPolicyKind},
+    strategy::Strategy,
+    symbol::get_symbols,
+};
 
 #[derive(Debug, PartialEq, Deserialize, Serialize, ToSchema, Clone)]
 pub enum Direction {
@@ -29,6 +53,16 @@ impl fmt::Display for Direction {
     }
 }
 
+impl Direction {
+    // 平仓时使用的 (side, positionSide)
+    pub fn close_sides(&self) -> (&'static str, &'static str) {
+        match self {
+            Direction::Long => ("SELL", "LONG"),
+            Direction::Short => ("BUY", "SHORT"),
+        }
+    }
+}
+
 impl FromStr for Direction {
     type Err = String;
 
@@ -40,23 +74,94 @@ impl FromStr for Direction {
         }
     }
 }
-#[derive(Debug, Clone, Serialize)]
+// 止损平仓最多尝试次数
+const EXIT_MAX_ATTEMPTS: u32 = 4;
+// 平仓重试的初始等待时间，之后每次翻倍
+const EXIT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+// 平仓失败后再次尝试的间隔（毫秒）
+const EXIT_RETRY_COOLDOWN_MS: i64 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionStatus {
+    #[default]
+    Open, // 持仓中
+    ExitPending, // 已触发止损，正在平仓
+    ExitFailed,  // 平仓重试耗尽，仍持有仓位，等待再次重试或人工处理
+    Closed,      // 已平仓
+}
+
+// 价格变化后需要在交易所执行的操作，由执行线程处理，价格线程不等待网络请求
+#[derive(Debug, Clone, PartialEq)]
+pub enum PositionAction {
+    ReplaceStop,           // 按最新止损价重挂止损单
+    PartialClose(Decimal), // 按比例减仓
+    Exit,                  // 市价平仓
+}
+
+// 模拟时每个价格点处理后的仓位状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationStep {
+    pub price: Decimal,
+    pub stop_loss: Decimal,
+    pub remaining: Decimal, // 减仓后剩余的仓位比例
+    pub exit: bool,         // 该价格触发了止损平仓或减仓后仓位已全部平掉
+}
+
+// 按时间平仓的规则，未设置的规则不生效
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ExitRules {
+    pub max_hold_hours: Option<u32>, // 开仓后超过该时间止损仍未移到保本以上时平仓
+    pub exit_before_funding_secs: Option<u64>, // 在资金费结算前该秒数内平仓
+}
+
+// 修改运行中仓位的参数，未设置的字段保持不变
+#[derive(Debug, Default, Clone)]
+pub struct PositionUpdate {
+    pub stop_loss: Option<Decimal>,
+    pub force: bool, // 允许放宽止损
+    pub strategies: Option<Vec<Strategy>>,
+    pub strategy_version_id: Option<String>, // strategies 来自用户策略时对应的版本
+    pub stop_policy: Option<StopPolicyConfig>,
+    pub exit_rules: Option<ExitRules>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub order_id: u64,
     pub user_id: String,
-    pub stop_order: u64,
-    pub symbol: String, // 货币或资产符号，表示此交易涉及的交易品种，如 "EUR/USD" 或 "AAPL"
-    pub entry_price: f64, // 入场价格，交易开始时的初始价格
-    pub stop_loss: f64, // 止损点位，如果当前价格达到该值，交易将自动平仓以限制损失
-    pub highest_price: f64, // 记录历史最高价格，用于动态调整止损点和判断利润情况（做多时）
-    pub lowest_price: f64, // 记录历史最低价格，用于动态调整止损点和判断利润情况（做空时）
-    pub direction: Direction, // 交易方向，标识是做多还是做空
+    pub stop_order: u64,        // 交易所止损单 ID，0 表示未挂单
+    pub symbol: String,         // 货币或资产符号，表示此交易涉及的交易品种，如 "EUR/USD" 或 "AAPL"
+    pub entry_price: Decimal,   // 入场价格，交易开始时的初始价格
+    pub stop_loss: Decimal,     // 止损点位，如果当前价格达到该值，交易将自动平仓以限制损失
+    pub highest_price: Decimal, // 记录历史最高价格，用于动态调整止损点和判断利润情况（做多时）
+    pub lowest_price: Decimal,  // 记录历史最低价格，用于动态调整止损点和判断利润情况（做空时）
+    pub direction: Direction,   // 交易方向，标识是做多还是做空
     pub quantity: String,
-    pub leverage: f64,
-    pub strategies: Vec<Strategy>,
-    pub is_closed: bool,
-    pub api_key: String,
-    pub api_secret: String,
+    #[serde(default)]
+    pub initial_quantity: String, // 开仓数量，分批止盈按该数量的比例减仓，为空时使用当前数量
+    pub leverage: Decimal,
+    #[serde(default)]
+    pub tick_size: Decimal, // 价格最小变动单位，止损价按此取整，0 表示不取整
+    #[serde(default)]
+    pub break_even_price: Decimal, // 计入往返手续费后的保本价，0 表示未知
+    pub stop_policy: StopPolicyKind, // 止损移动方式
+    #[serde(default)]
+    pub strategy_version_id: Option<String>, // 当前档位来自的用户策略版本，为空表示直接指定的档位
+    #[serde(default)]
+    pub exit_rules: ExitRules,
+    #[serde(default)]
+    pub opened_at: i64, // 开仓时间（毫秒），0 表示未知
+    #[serde(default)]
+    pub status: PositionStatus,
+    #[serde(default)]
+    pub exit_failed_at: i64, // 最近一次平仓失败的时间（毫秒）
+    #[serde(default)]
+    pub exit_attempts: u32, // 已发送的平仓单数量，用于生成平仓单 ID
+    #[serde(skip)]
+    pub stop_pending: bool, // 止损价已变化，等待执行线程重挂止损单
+    #[serde(skip)]
+    pub account: SecretKey, // 下单账户不落库，开仓和恢复仓位时由调用方设置
 }
 
 impl Position {
@@ -65,166 +170,672 @@ impl Position {
         order_id: u64,
         user_id: String,
         symbol: String,
-        entry_price: f64,
+        entry_price: Decimal,
         direction: Direction,
         quantity: String,
-        leverage: f64,
-        stop_loss_percent: f64,
-        mut strategies: Vec<Strategy>,
-        api_key: String,
-        api_secret: String,
+        leverage: Decimal,
+        stop_loss_percent: Decimal,
+        stop_policy: StopPolicyKind,
     ) -> Self {
-        let stop_loss = calculate_stop_price(&direction, entry_price, leverage, stop_loss_percent);
-
-        let stop_order_id = order_id;
-
-        strategies.push(Strategy {
-            max: 1.1,
-            adjustment: 0.1,
-        });
+        let tick_size = get_symbol_tick_size(&symbol).await.unwrap_or(Decimal::ZERO);
+        let stop_loss = round_to_tick(
+            calculate_stop_price(&direction, entry_price, leverage, stop_loss_percent),
+            tick_size,
+        );
 
         Self {
             user_id,
             order_id,
-            stop_order: stop_order_id,
+            stop_order: 0,
             symbol,
             entry_price,
             stop_loss,
             highest_price: entry_price, // 做多时初始为入场价
             lowest_price: entry_price,  // 做空时初始为入场价
             direction,
+            initial_quantity: quantity.clone(),
             quantity,
             leverage,
-            strategies,
-            is_closed: false,
-            api_key,
-            api_secret,
+            tick_size,
+            break_even_price: Decimal::ZERO,
+            stop_policy,
+            strategy_version_id: None,
+            exit_rules: ExitRules::default(),
+            opened_at: Utc::now().timestamp_millis(),
+            status: PositionStatus::Open,
+            exit_failed_at: 0,
+            exit_attempts: 0,
+            stop_pending: false,
+            account: SecretKey::default(),
         }
     }
 
-    // 更新价格并调整历史最高或最低价和止损
-    pub async fn update_price(&mut self, book_price: (String, String)) {
-        let price = match self.direction {
+    // 不关联交易所的模拟仓位，用于策略模拟
+    pub fn simulated(
+        direction: Direction,
+        entry_price: Decimal,
+        leverage: Decimal,
+        stop_loss_percent: Decimal,
+        stop_policy: StopPolicyKind,
+    ) -> Self {
+        let stop_loss = calculate_stop_price(&direction, entry_price, leverage, stop_loss_percent);
+        Self {
+            user_id: String::new(),
+            order_id: 0,
+            stop_order: 0,
+            symbol: String::new(),
+            entry_price,
+            stop_loss,
+            highest_price: entry_price,
+            lowest_price: entry_price,
+            direction,
+            quantity: "1".to_string(),
+            initial_quantity: "1".to_string(),
+            leverage,
+            tick_size: Decimal::ZERO,
+            break_even_price: Decimal::ZERO,
+            stop_policy,
+            strategy_version_id: None,
+            exit_rules: ExitRules::default(),
+            opened_at: 0,
+            status: PositionStatus::Open,
+            exit_failed_at: 0,
+            exit_attempts: 0,
+            stop_pending: false,
+            account: SecretKey::default(),
+        }
+    }
+
+    // 依次处理价格序列但不下单，返回每个价格之后的止损，触发平仓后停止
+    pub fn simulate(&mut self, prices: &[Decimal], interval_ms: i64) -> Vec<SimulationStep> {
+        let mut steps = Vec::with_capacity(prices.len());
+        let mut remaining = Decimal::ONE;
+        for (i, price) in prices.iter().enumerate() {
+            let (_, actions) = self.apply_price(*price, self.opened_at + i as i64 * interval_ms);
+            let mut exit = false;
+            for action in actions {
+                match action {
+                    // 模拟中止损单总是立即重挂成功
+                    PositionAction::ReplaceStop => self.stop_pending = false,
+                    // 减仓比例是开仓数量的比例，与实盘和回测一致
+                    PositionAction::PartialClose(fraction) => {
+                        remaining = (remaining - fraction.min(Decimal::ONE)).max(Decimal::ZERO);
+                        exit |= remaining.is_zero();
+                    }
+                    PositionAction::Exit => exit = true,
+                }
+            }
+            steps.push(SimulationStep {
+                price: *price,
+                stop_loss: self.stop_loss,
+                remaining,
+                exit,
+            });
+            if exit {
+                break;
+            }
+        }
+        steps
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.status == PositionStatus::Closed
+    }
+
+    // 是否属于指定用户和方向的未平仓仓位，order_id 为 None 时匹配整组
+    fn is_target(&self, user_id: &str, direction: &Direction, order_id: Option<u64>) -> bool {
+        let lot = match order_id {
+            Some(order_id) => self.order_id == order_id,
+            None => true,
+        };
+        lot && self.user_id == user_id && self.direction == *direction && !self.is_closed()
+    }
+
+    // 止损是否已经移到入场价或更有利的位置
+    fn profit_locked(&self) -> bool {
+        match self.direction {
+            Direction::Long => self.stop_loss >= self.entry_price,
+            Direction::Short => self.stop_loss <= self.entry_price,
+        }
+    }
+
+    // 检查按时间平仓的规则，触发时标记为平仓中并返回平仓操作，next_funding 为下一次资金费结算时间
+    pub fn check_time_exit(
+        &mut self,
+        now: i64,
+        next_funding: Option<i64>,
+    ) -> Option<PositionAction> {
+        if self.status != PositionStatus::Open {
+            return None;
+        }
+
+        let mut reason = None;
+        if let Some(hours) = self.exit_rules.max_hold_hours {
+            let deadline = self.opened_at + i64::from(hours) * 3_600_000;
+            if self.opened_at > 0 && now >= deadline && !self.profit_locked() {
+                reason = Some("持仓超时");
+            }
+        }
+        if let (Some(secs), Some(funding)) =
+            (self.exit_rules.exit_before_funding_secs, next_funding)
+        {
+            let window_start = funding - secs as i64 * 1000;
+            // 在结算窗口内才开的仓位不受本次结算影响
+            if now >= window_start && now < funding && self.opened_at < window_start {
+                reason = Some("资金费结算前");
+            }
+        }
+
+        let reason = reason?;
+        println!(
+            "{}平仓，交易对 {}， 方向{:?}, 开仓价格: {}, 关闭交易 ID {}。",
+            reason, self.symbol, self.direction, self.entry_price, self.order_id
+        );
+        self.status = PositionStatus::ExitPending;
+        Some(PositionAction::Exit)
+    }
+
+    // 下单时使用的账户，纸面交易仓位指向模拟交易所
+    pub fn exchange(&self) -> Box<dyn Exchange> {
+        exchange(&self.account)
+    }
+
+    // 按用户手续费率和交易所给出的 breakEvenPrice 计算保本价，取更保守的一个
+    pub async fn update_break_even_price(&mut self) {
+        let mut break_even_price = Decimal::ZERO;
+        let exchange = self.exchange();
+        match exchange.commission_rate(&self.symbol).await {
+            Ok(rate) => {
+                break_even_price = fee_break_even_price(
+                    &self.direction,
+                    self.entry_price,
+                    rate.taker_commission_rate,
+                )
+            }
+            Err(e) => eprintln!("Get commission rate error: {:?}", e),
+        }
+
+        let (_, position_side) = self.direction.close_sides();
+        let symbol = self.symbol.to_uppercase();
+        match exchange.get_risk().await {
+            Ok(risks) => {
+                let risk = risks
+                    .iter()
+                    .find(|r| r.symbol == symbol && r.position_side == position_side);
+                if let Some(risk) = risk.filter(|r| r.break_even_price > Decimal::ZERO) {
+                    break_even_price = match self.direction {
+                        Direction::Long => break_even_price.max(risk.break_even_price),
+                        Direction::Short if break_even_price.is_zero() => risk.break_even_price,
+                        Direction::Short => break_even_price.min(risk.break_even_price),
+                    };
+                }
+            }
+            Err(e) => eprintln!("Get risk error: {:?}", e),
+        }
+        self.break_even_price = break_even_price;
+    }
+
+    // 锁定利润的止损（做多高于入场价、做空低于入场价）至少移到保本价，保本价已越过当前价格时不调整
+    fn apply_break_even_floor(&self, stop_loss: Decimal) -> Decimal {
+        if self.break_even_price.is_zero() {
+            return stop_loss;
+        }
+        // 保本价按 tick 向不利于成交的方向取整，保证覆盖手续费
+        let floor = round_to_tick(self.break_even_price, self.tick_size);
+        match self.direction {
             Direction::Long => {
-                let price = book_price.1;
-                let price_f64: f64 = price.parse().unwrap();
-                if price_f64 > self.highest_price {
-                    self.highest_price = price_f64;
-                    let profit_percentage =
-                        (self.highest_price - self.entry_price) / self.entry_price;
-                    self.update_stop_loss(profit_percentage, true).await;
+                let floor = if floor < self.break_even_price {
+                    floor + self.tick_size
+                } else {
+                    floor
+                };
+                if stop_loss >= self.entry_price && stop_loss < floor && floor < self.highest_price
+                {
+                    floor
+                } else {
+                    stop_loss
                 }
-                price
             }
             Direction::Short => {
-                let price = book_price.0;
-                let price_f64: f64 = price.parse().unwrap();
-                if price_f64 < self.lowest_price {
-                    self.lowest_price = price_f64;
-                    let profit_percentage =
-                        (self.entry_price - self.lowest_price) / self.entry_price;
-                    self.update_stop_loss(profit_percentage, false).await;
+                let floor = if floor > self.break_even_price {
+                    floor - self.tick_size
+                } else {
+                    floor
+                };
+                if stop_loss <= self.entry_price && stop_loss > floor && floor > self.lowest_price {
+                    floor
+                } else {
+                    stop_loss
                 }
-                price
             }
+        }
+    }
+
+    // 已乘杠杆的历史最大收益率
+    fn peak_profit(&self) -> Decimal {
+        let change = match self.direction {
+            Direction::Long => self.highest_price - self.entry_price,
+            Direction::Short => self.entry_price - self.lowest_price,
         };
-        self.check_exit_conditions(&price).await;
+        change / self.entry_price * self.leverage
     }
 
-    async fn update_stop_loss(&mut self, profit_percentage: f64, is_long: bool) {
-        let new_stop_price = self.calculate_new_stop_loss(profit_percentage, is_long);
+    // 修改止损价或止损方式，market_price 为平仓方向的盘口价，返回是否需要重挂止损单
+    fn apply_update(&mut self, update: PositionUpdate, market_price: Decimal) -> Result<bool> {
+        if self.status != PositionStatus::Open {
+            return Err(Error::ErrorCode(error_code::POSITION_NOT_OPEN.0));
+        }
 
-        if new_stop_price != self.stop_loss {
-            self.stop_loss = new_stop_price;
+        // 先校验全部参数，避免部分修改生效
+        if let Some(config) = &update.stop_policy {
+            config
+                .validate(self.leverage)
+                .map_err(|code| Error::ErrorCode(code.0))?;
+        }
+        let stop_loss = match update.stop_loss {
+            Some(stop_loss) => {
+                let stop_loss = round_to_tick(stop_loss, self.tick_size);
+                // 止损价必须在盘口价的亏损一侧，否则会立即触发
+                let (valid, tighter) = match self.direction {
+                    Direction::Long => (
+                        stop_loss > Decimal::ZERO && stop_loss < market_price,
+                        stop_loss >= self.stop_loss,
+                    ),
+                    Direction::Short => (stop_loss > market_price, stop_loss <= self.stop_loss),
+                };
+                if !valid {
+                    return Err(Error::ErrorCode(error_code::INVALID_STOP_LOSS.0));
+                }
+                if !tighter && !update.force {
+                    return Err(Error::ErrorCode(error_code::STOP_LOSS_LOOSENED.0));
+                }
+                Some(stop_loss)
+            }
+            None => None,
+        };
+
+        let is_tiered = matches!(self.stop_policy, StopPolicyKind::Tiered(_));
+        let has_strategies = update.strategies.is_some();
+        let stop_policy = match (update.stop_policy, update.strategies) {
+            (None, None) => None,
+            // 只传入档位时替换阶梯止损剩余的档位
+            (None, Some(strategies)) if is_tiered => Some(StopPolicyKind::from_config(
+                StopPolicyConfig::Tiered,
+                strategies,
+            )),
+            // 切换到阶梯止损但没有给出档位时，沿用当前的档位
+            (Some(StopPolicyConfig::Tiered), None) if is_tiered => None,
+            (None, Some(_)) | (Some(StopPolicyConfig::Tiered), None) => {
+                return Err(Error::ErrorCode(error_code::INVALID_STOP_POLICY.0));
+            }
+            (Some(config), strategies) => Some(StopPolicyKind::from_config(
+                config,
+                strategies.unwrap_or_default(),
+            )),
+        };
+
+        if let Some(mut stop_policy) = stop_policy {
+            stop_policy.skip_reached_tiers(self.peak_profit());
+            self.stop_policy = stop_policy;
+            self.strategy_version_id = if has_strategies {
+                update.strategy_version_id
+            } else {
+                None
+            };
+        }
+        if let Some(exit_rules) = update.exit_rules {
+            self.exit_rules = exit_rules;
+        }
+        match stop_loss {
+            Some(stop_loss) if stop_loss != self.stop_loss => {
+                self.stop_loss = stop_loss;
+                let submit = !self.stop_pending;
+                self.stop_pending = true;
+                Ok(submit)
+            }
+            _ => Ok(false),
         }
     }
 
-    fn calculate_new_stop_loss(&mut self, profit_percentage: f64, is_long: bool) -> f64 {
-        let actual_price_change_percentage = profit_percentage * self.leverage;
+    // 更新价格并调整历史最高或最低价和止损，返回仓位状态是否有变化以及需要在交易所执行的操作
+    pub fn update_price(&mut self, book_price: (String, String)) -> (bool, Vec<PositionAction>) {
+        // 做多按买一价、做空按卖一价判断
+        let price = match self.direction {
+            Direction::Long => book_price.1,
+            Direction::Short => book_price.0,
+        };
+        let price: Decimal = match price.parse() {
+            Ok(price) => price,
+            Err(e) => {
+                eprintln!("Invalid price {} for {}: {:?}", price, self.symbol, e);
+                return (false, Vec::new());
+            }
+        };
+        self.apply_price(price, Utc::now().timestamp_millis())
+    }
 
-        let adjustement = get_adjustment(actual_price_change_percentage, &mut self.strategies);
+    // 按指定时间处理一个价格，模拟和回测时使用行情自带的时间
+    pub fn apply_price(&mut self, price: Decimal, now: i64) -> (bool, Vec<PositionAction>) {
+        let mut changed = false;
+        let mut actions = Vec::new();
+        // 止损已触发后不再移动止损，只处理平仓
+        if self.status == PositionStatus::Open {
+            self.stop_policy.observe(price, now);
+            match self.direction {
+                Direction::Long => {
+                    if price > self.highest_price {
+                        changed = true;
+                        self.highest_price = price;
+                        let profit_percentage =
+                            (self.highest_price - self.entry_price) / self.entry_price;
+                        self.update_stop_loss(profit_percentage, true, &mut actions);
+                    }
+                }
+                Direction::Short => {
+                    if price < self.lowest_price {
+                        changed = true;
+                        self.lowest_price = price;
+                        let profit_percentage =
+                            (self.entry_price - self.lowest_price) / self.entry_price;
+                        self.update_stop_loss(profit_percentage, false, &mut actions);
+                    }
+                }
+            }
+        }
+        if let Some(action) = self.check_exit_conditions(price, now) {
+            changed = true;
+            actions.push(action);
+        }
+        (changed, actions)
+    }
 
-        println!("adjustement: {}", adjustement);
+    fn update_stop_loss(
+        &mut self,
+        profit_percentage: Decimal,
+        is_long: bool,
+        actions: &mut Vec<PositionAction>,
+    ) {
+        let (new_stop_price, close_fraction) =
+            self.calculate_new_stop_loss(profit_percentage, is_long);
+        let new_stop_price =
+            self.apply_break_even_floor(round_to_tick(new_stop_price, self.tick_size));
 
-        if adjustement == 0.0 {
-            return self.stop_loss;
+        // 减仓完成后执行线程会按剩余数量重挂止损单
+        if close_fraction > Decimal::ZERO {
+            actions.push(PositionAction::PartialClose(close_fraction));
         }
 
-        if is_long {
-            if actual_price_change_percentage >= 1.09 {
-                self.highest_price * (1.0 - adjustement / self.leverage)
-            } else {
-                self.entry_price * (1.0 + adjustement / self.leverage)
+        // 止损只允许朝有利方向移动
+        let tighter = if is_long {
+            new_stop_price > self.stop_loss
+        } else {
+            new_stop_price < self.stop_loss
+        };
+        if tighter {
+            self.stop_loss = new_stop_price;
+            // 已有待执行的重挂请求时不重复投递，执行时会使用最新的止损价
+            if !self.stop_pending {
+                self.stop_pending = true;
+                actions.push(PositionAction::ReplaceStop);
             }
+        }
+    }
+
+    // 按开仓数量的比例市价减仓锁定部分利润，减仓成功时返回 true
+    async fn take_partial_profit(&mut self, close_fraction: Decimal) -> bool {
+        let quantity: Decimal = self.quantity.parse().unwrap_or(Decimal::ZERO);
+        let initial_quantity = self.initial_quantity.parse().unwrap_or(quantity);
+        let precision = get_symbol_percision(&self.symbol).await.unwrap_or(0) as u32;
+        // 向下取整到数量精度，超过剩余持仓时全部平仓
+        let close_quantity = (initial_quantity * close_fraction.min(Decimal::ONE))
+            .round_dp_with_strategy(precision, RoundingStrategy::ToZero)
+            .min(quantity);
+        if close_quantity <= Decimal::ZERO {
+            return false;
+        }
+
+        let remain_quantity = quantity - close_quantity;
+        let full_close = remain_quantity <= Decimal::ZERO;
+        let close_quantity = if full_close {
+            self.quantity.clone()
         } else {
-            if actual_price_change_percentage >= 1.09 {
-                self.lowest_price * (1.0 + adjustement / self.leverage)
-            } else {
-                self.entry_price * (1.0 - adjustement / self.leverage)
+            close_quantity.normalize().to_string()
+        };
+
+        let (side, position_side) = self.direction.close_sides();
+        if let Err(e) = create_position_order(
+            self.exchange().as_ref(),
+            &self.symbol,
+            side,
+            position_side,
+            &close_quantity,
+        )
+        .await
+        {
+            eprintln!("Take partial profit error: {:?}", e);
+            return false;
+        }
+        println!(
+            "分批止盈 {}，交易对 {}，方向{:?}，平仓数量 {}，交易 ID {}。",
+            close_fraction, self.symbol, self.direction, close_quantity, self.order_id
+        );
+
+        if full_close {
+            self.cancel_stop_order().await;
+            self.status = PositionStatus::Closed;
+        } else {
+            self.quantity = remain_quantity.normalize().to_string();
+        }
+        true
+    }
+
+    // 在交易所挂出止损单，已有止损单时先挂新单再撤旧单，避免出现无保护的间隙
+    pub async fn place_stop_order(&mut self) {
+        let stop_price = self.stop_loss.normalize().to_string();
+        let (side, position_side) = self.direction.close_sides();
+        match create_stop_order(
+            self.exchange().as_ref(),
+            &self.symbol,
+            side,
+            position_side,
+            &self.quantity,
+            &stop_price,
+        )
+        .await
+        {
+            Ok(order) => {
+                self.cancel_stop_order().await;
+                self.stop_order = order.order_id;
             }
+            Err(e) => eprintln!("Create stop order error: {:?}", e),
         }
     }
 
-    // 检查是否应平仓
-    async fn check_exit_conditions(&mut self, price: &str) {
-        // 如果交易已平仓，直接返回，不打印
-        if self.is_closed {
+    // 撤销交易所止损单
+    pub async fn cancel_stop_order(&mut self) {
+        if self.stop_order == 0 {
             return;
         }
-        let price_f64: f64 = price.parse().unwrap();
+        if let Err(e) =
+            cancel_stop_order(self.exchange().as_ref(), &self.symbol, self.stop_order).await
+        {
+            eprintln!("Cancel stop order {} error: {:?}", self.stop_order, e);
+        }
+        self.stop_order = 0;
+    }
+
+    // 返回新的止损价以及本次需要减仓的比例
+    fn calculate_new_stop_loss(
+        &mut self,
+        profit_percentage: Decimal,
+        is_long: bool,
+    ) -> (Decimal, Decimal) {
+        let ctx = StopContext {
+            is_long,
+            entry_price: self.entry_price,
+            leverage: self.leverage,
+            stop_loss: self.stop_loss,
+            highest_price: self.highest_price,
+            lowest_price: self.lowest_price,
+            profit_percentage,
+        };
+        let decision = self.stop_policy.next_stop(&ctx);
+        (decision.stop_loss, decision.close_fraction)
+    }
 
-        if (self.direction == Direction::Long && price_f64 <= self.stop_loss)
-            || (self.direction == Direction::Short && price_f64 >= self.stop_loss)
+    // 检查是否应平仓，触发平仓时标记为平仓中并返回平仓操作
+    fn check_exit_conditions(&mut self, price: Decimal, now: i64) -> Option<PositionAction> {
+        match self.status {
+            // 如果交易已平仓或正在平仓，直接返回，不打印
+            PositionStatus::Closed | PositionStatus::ExitPending => return None,
+            PositionStatus::ExitFailed => {
+                // 止损已触发但未能平仓，冷却结束后不论当前价格继续尝试
+                if now - self.exit_failed_at < EXIT_RETRY_COOLDOWN_MS {
+                    return None;
+                }
+                self.status = PositionStatus::ExitPending;
+                return Some(PositionAction::Exit);
+            }
+            PositionStatus::Open => {}
+        }
+
+        if (self.direction == Direction::Long && price <= self.stop_loss)
+            || (self.direction == Direction::Short && price >= self.stop_loss)
         {
             println!(
                 "止损触发于 {}，交易对 {}， 方向{:?}, 开仓价格: {}, 关闭交易 ID {}。",
                 price, self.symbol, self.direction, self.entry_price, self.order_id
             );
-            let (side, position_side) = match self.direction {
-                Direction::Long => ("SELL", "LONG"),
-                Direction::Short => ("BUY", "SHORT"),
-            };
-            let _ = create_position_order(
-                &self.symbol,
-                side,
-                position_side,
-                &self.quantity,
-                &self.api_key,
-                &self.api_secret,
-            )
-            .await
-            .map_err(|e| {
-                eprintln!("Create position error: {:?}", e);
-            });
+            self.status = PositionStatus::ExitPending;
+            return Some(PositionAction::Exit);
+        }
+        None
+    }
+
+    // 市价平仓，失败时按指数退避重试，重试耗尽后标记为平仓失败并告警
+    async fn execute_exit(&mut self) {
+        let retrying = self.exit_failed_at != 0;
+        let exchange = self.exchange();
+        let mut last_error = String::new();
+        for attempt in 0..EXIT_MAX_ATTEMPTS {
+            if attempt > 0 {
+                sleep(EXIT_RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+            }
+            match self.send_exit_order(exchange.as_ref()).await {
+                Ok(()) => {
+                    self.finish_exit().await;
+                    return;
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Exit position {} attempt {} error: {:?}",
+                        self.order_id,
+                        attempt + 1,
+                        e
+                    );
+                    last_error = format!("{:?}", e);
+                }
+            }
+        }
 
-            // 设置为已平仓状态
-            self.is_closed = true;
+        // 交易所止损单保持不动，作为最后一道保护
+        self.status = PositionStatus::ExitFailed;
+        self.exit_failed_at = Utc::now().timestamp_millis();
+        if retrying {
+            eprintln!(
+                "Exit position {} still failing: {}",
+                self.order_id, last_error
+            );
+        } else {
+            // 只在首次失败时告警，之后的重试只打印日志
+            tokio::spawn(report_exit_failure(self.clone(), last_error));
         }
     }
+
+    // 平仓单带上由交易 ID 和发送次数生成的 clientOrderId。上一次发送可能已被交易所接受但响应丢失，
+    // 重发前先按 ID 查询，已存在有效订单时视为平仓单已挂出；同方向可能还有其他仓位，不能只看方向是否已无持仓
+    async fn send_exit_order(&mut self, exchange: &dyn Exchange) -> Result<()> {
+        if self.exit_attempts > 0 {
+            let previous = exit_client_order_id(self.order_id, self.exit_attempts - 1);
+            let order = exchange.find_order(&self.symbol, &previous).await?;
+            if order
+                .is_some_and(|o| !matches!(o.status.as_str(), "CANCELED" | "EXPIRED" | "REJECTED"))
+            {
+                return Ok(());
+            }
+        }
+
+        let client_order_id = exit_client_order_id(self.order_id, self.exit_attempts);
+        self.exit_attempts += 1;
+        let (side, position_side) = self.direction.close_sides();
+        exchange
+            .create_order(
+                OrderRequest::market(&self.symbol, side, position_side, &self.quantity)
+                    .client_order_id(&client_order_id),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn finish_exit(&mut self) {
+        self.cancel_stop_order().await;
+        // 设置为已平仓状态
+        self.status = PositionStatus::Closed;
+    }
 }
 
-pub fn calculate_stop_price(
+// 第 attempt 次发送的平仓单 ID，币安限制为 36 个字符
+fn exit_client_order_id(order_id: u64, attempt: u32) -> String {
+    format!("exit_{}_{}", order_id, attempt)
+}
+
+// 记录平仓失败事件并发出告警
+async fn report_exit_failure(position: Position, error: String) {
+    let detail = format!(
+        "交易对 {}，方向{:?}，数量 {}，止损价 {}，交易 ID {}，重试 {} 次后仍未平仓: {}",
+        position.symbol,
+        position.direction,
+        position.quantity,
+        position.stop_loss,
+        position.order_id,
+        EXIT_MAX_ATTEMPTS,
+        error
+    );
+    let event = CreatePositionEventRequest {
+        user_id: position.user_id.clone(),
+        order_id: position.order_id,
+        symbol: position.symbol.clone(),
+        kind: PositionEventKind::ExitFailed,
+        detail: detail.clone(),
+    };
+    if let Err(e) = db_create_position_event(event).await {
+        eprintln!("record exit failure {} error: {:?}", position.order_id, e);
+    }
+    send_alert(&format!("用户 {} 平仓失败", position.user_id), &detail).await;
+}
+
+// 市价开仓、市价平仓各付一次吃单手续费后的保本价
+pub fn fee_break_even_price(
     direction: &Direction,
-    price: f64,
-    leverage: f64,
-    stop_loss_percent: f64,
-) -> f64 {
+    entry_price: Decimal,
+    taker_rate: Decimal,
+) -> Decimal {
     match direction {
-        Direction::Long => price * (1.0 - stop_loss_percent / leverage), // 做多时根据杠杆倍数和调整参数设置止损
-        Direction::Short => price * (1.0 + stop_loss_percent / leverage), // 做空时根据杠杆倍数和调整参数设置止损
+        Direction::Long => entry_price * (Decimal::ONE + taker_rate) / (Decimal::ONE - taker_rate),
+        Direction::Short => entry_price * (Decimal::ONE - taker_rate) / (Decimal::ONE + taker_rate),
     }
 }
 
-fn get_adjustment(percentage: f64, strategies: &mut Vec<Strategy>) -> f64 {
-    let strategy = strategies.iter().find(|adj| percentage >= adj.max);
-    let r = strategy.map_or_else(|| 0.0, |adj| adj.adjustment);
-    match strategy {
-        Some(_s) => {
-            strategies.retain(|adj| percentage < adj.max);
-        }
-        None => {}
+pub fn calculate_stop_price(
+    direction: &Direction,
+    price: Decimal,
+    leverage: Decimal,
+    stop_loss_percent: Decimal,
+) -> Decimal {
+    match direction {
+        Direction::Long => price * (Decimal::ONE - stop_loss_percent / leverage), // 做多时根据杠杆倍数和调整参数设置止损
+        Direction::Short => price * (Decimal::ONE + stop_loss_percent / leverage), // 做空时根据杠杆倍数和调整参数设置止损
     }
-    r
 }
 
 static POSITION: LazyLock<Arc<PositionManager>> = LazyLock::new(PositionManager::new);
@@ -256,19 +867,45 @@ impl PositionManager {
     async fn clear_position(&self, symbol: &str) {
         if let Some(mutex_vec) = self.keys.get(symbol) {
             let mut vec = mutex_vec.lock().await;
-            vec.retain(|t| if t.is_closed { false } else { true });
+            vec.retain(|t| !t.is_closed());
         }
     }
 
     async fn update_position_price(&self, symbol: &str, price: (String, String)) {
+        let triggered_at = Instant::now();
         if let Some(mutex_vec) = self.keys.get(symbol) {
             let mut vec = mutex_vec.lock().await;
             for t in vec.iter_mut() {
-                t.update_price(price.clone()).await;
+                let (changed, actions) = t.update_price(price.clone());
+                if changed {
+                    save_position(t.clone());
+                }
+                for action in actions {
+                    submit_job(ExecutionJob {
+                        symbol: symbol.to_string(),
+                        order_id: t.order_id,
+                        action,
+                        triggered_at,
+                    });
+                }
             }
         }
     }
 
+    // 在锁内修改指定仓位并返回修改后的快照，仓位不存在时返回 None
+    async fn modify_position<F: FnOnce(&mut Position)>(
+        &self,
+        symbol: &str,
+        order_id: u64,
+        f: F,
+    ) -> Option<Position> {
+        let mutex_vec = self.keys.get(symbol)?;
+        let mut vec = mutex_vec.lock().await;
+        let position = vec.iter_mut().find(|t| t.order_id == order_id)?;
+        f(position);
+        Some(position.clone())
+    }
+
     async fn remove_user_symbol_direction_position(
         &self,
         symbol: &str,
@@ -276,14 +913,160 @@ impl PositionManager {
         direction: &Direction,
     ) {
         if let Some(mutex_vec) = self.keys.get(symbol.to_lowercase().as_str()) {
+            let removed = {
+                let mut vec = mutex_vec.lock().await;
+                let (removed, kept): (Vec<Position>, Vec<Position>) = vec
+                    .drain(..)
+                    .partition(|t| t.user_id == user_id && t.direction == *direction);
+                *vec = kept;
+                removed
+            };
+            // 撤单在释放锁之后进行
+            for mut closed in removed {
+                closed.cancel_stop_order().await;
+                closed.status = PositionStatus::Closed;
+                save_position(closed);
+            }
+        }
+    }
+
+    // 从内存中移除指定仓位
+    async fn remove_position(&self, symbol: &str, order_id: u64) -> Option<Position> {
+        let mutex_vec = self.keys.get(symbol)?;
+        let mut vec = mutex_vec.lock().await;
+        let index = vec.iter().position(|t| t.order_id == order_id)?;
+        Some(vec.remove(index))
+    }
+
+    // 手动平仓前将仓位标记为平仓中，避免与止损平仓重复下单，返回标记前的仓位
+    async fn claim_user_position_exit(
+        &self,
+        symbol: &str,
+        user_id: &str,
+        direction: &Direction,
+        order_id: u64,
+    ) -> Result<Position> {
+        let mutex_vec = self
+            .keys
+            .get(symbol)
+            .ok_or(Error::ErrorCode(error_code::INVALIAD_SYMBOLE.0))?;
+        let mut vec = mutex_vec.lock().await;
+        let position = vec
+            .iter_mut()
+            .find(|t| t.is_target(user_id, direction, Some(order_id)))
+            .ok_or(Error::ErrorCode(error_code::POSITION_NOT_FOUND.0))?;
+        if position.status == PositionStatus::ExitPending {
+            return Err(Error::ErrorCode(error_code::POSITION_NOT_OPEN.0));
+        }
+        let claimed = position.clone();
+        position.status = PositionStatus::ExitPending;
+        Ok(claimed)
+    }
+
+    // 修改单个仓位或整组仓位，整组修改时任一仓位校验失败则全部不生效
+    async fn update_user_positions(
+        &self,
+        symbol: &str,
+        user_id: &str,
+        direction: &Direction,
+        order_id: Option<u64>,
+        update: PositionUpdate,
+        market_price: Decimal,
+    ) -> Result<Vec<Position>> {
+        let mutex_vec = self
+            .keys
+            .get(symbol)
+            .ok_or(Error::ErrorCode(error_code::INVALIAD_SYMBOLE.0))?;
+        let mut vec = mutex_vec.lock().await;
+
+        let mut updated = Vec::new();
+        for (index, t) in vec.iter().enumerate() {
+            if !t.is_target(user_id, direction, order_id) {
+                continue;
+            }
+            let mut position = t.clone();
+            let submit = position.apply_update(update.clone(), market_price)?;
+            updated.push((index, position, submit));
+        }
+        if updated.is_empty() {
+            return Err(Error::ErrorCode(error_code::POSITION_NOT_FOUND.0));
+        }
+
+        let mut positions = Vec::new();
+        for (index, position, submit) in updated {
+            if submit {
+                submit_job(ExecutionJob {
+                    symbol: symbol.to_string(),
+                    order_id: position.order_id,
+                    action: PositionAction::ReplaceStop,
+                    triggered_at: Instant::now(),
+                });
+            }
+            vec[index] = position.clone();
+            positions.push(position);
+        }
+        Ok(positions)
+    }
+
+    // 检查全部仓位的按时间平仓规则，触发的仓位交给执行线程平仓
+    async fn check_time_exits(&self, now: i64, funding_times: &HashMap<String, i64>) {
+        let triggered_at = Instant::now();
+        for (symbol, mutex_vec) in self.keys.iter() {
+            let next_funding = funding_times.get(symbol).copied();
             let mut vec = mutex_vec.lock().await;
-            vec.retain(|t| {
-                if t.user_id == user_id && t.direction == *direction {
-                    false
-                } else {
-                    true
+            for t in vec.iter_mut() {
+                if let Some(action) = t.check_time_exit(now, next_funding) {
+                    save_position(t.clone());
+                    submit_job(ExecutionJob {
+                        symbol: symbol.clone(),
+                        order_id: t.order_id,
+                        action,
+                        triggered_at,
+                    });
                 }
-            });
+            }
+        }
+    }
+
+    async fn get_all_positions(&self) -> Vec<Position> {
+        let mut positions = Vec::new();
+        for mutex_vec in self.keys.values() {
+            let vec = mutex_vec.lock().await;
+            positions.extend(vec.iter().filter(|t| !t.is_closed()).cloned());
+        }
+        positions
+    }
+
+    // 按交易所实际持仓修正仓位数量，quantity 为 None 时移除该仓位
+    async fn reconcile_position(
+        &self,
+        symbol: &str,
+        order_id: u64,
+        quantity: Option<String>,
+    ) -> Option<Position> {
+        let mutex_vec = self.keys.get(symbol)?;
+        let mut vec = mutex_vec.lock().await;
+        let index = vec.iter().position(|t| t.order_id == order_id)?;
+        match quantity {
+            Some(quantity) => {
+                // 数量变化后由执行线程按新数量重挂止损单
+                vec[index].quantity = quantity;
+                vec[index].stop_pending = true;
+                submit_job(ExecutionJob {
+                    symbol: symbol.to_string(),
+                    order_id,
+                    action: PositionAction::ReplaceStop,
+                    triggered_at: Instant::now(),
+                });
+                Some(vec[index].clone())
+            }
+            None => {
+                let mut removed = vec.remove(index);
+                drop(vec);
+                removed.cancel_stop_order().await;
+                removed.status = PositionStatus::Closed;
+                Some(removed)
+            }
         }
     }
 
@@ -292,16 +1075,15 @@ impl PositionManager {
         symbol: &str,
         deriction: &Direction,
         user_id: &str,
-    ) -> Option<Position> {
+    ) -> Vec<Position> {
         if let Some(mutex_vec) = self.keys.get(symbol) {
             let vec = mutex_vec.lock().await;
-            let t = vec.clone();
-            let r = t
-                .into_iter()
-                .find(|t| t.direction == *deriction && t.user_id == user_id);
-            r
+            vec.iter()
+                .filter(|t| t.is_target(user_id, deriction, None))
+                .cloned()
+                .collect()
         } else {
-            None
+            Vec::new()
         }
     }
 }
@@ -314,6 +1096,139 @@ pub async fn inser_user_positon(position: Position) -> Result<()> {
     get_position_manager().insert_position(position).await
 }
 
+// 服务启动时从数据库恢复未平仓的仓位
+pub async fn restore_positions() -> Result<()> {
+    let positions = db_get_open_positions().await?;
+    println!("restore positions: {}", positions.len());
+    for mut position in positions {
+        let order_id = position.order_id;
+        // 落库的仓位不含账户信息，按用户重新设置
+        match load_secret_key(&position.user_id).await {
+            Ok(key) => position.account = key,
+            Err(e) => {
+                eprintln!("restore position {} account error: {:?}", order_id, e);
+                continue;
+            }
+        }
+        let symbol = position.symbol.clone();
+        // 重启前未完成的平仓重新投递
+        let exit_pending = position.status == PositionStatus::ExitPending;
+        if let Err(e) = inser_user_positon(position).await {
+            eprintln!("restore position {} error: {:?}", order_id, e);
+            continue;
+        }
+        if exit_pending {
+            submit_job(ExecutionJob {
+                symbol,
+                order_id,
+                action: PositionAction::Exit,
+                triggered_at: Instant::now(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// 执行线程处理仓位操作，交易所请求期间不持有交易对的锁，止损和平仓请求不受限频排队影响
+pub async fn execute_position_action(symbol: &str, order_id: u64, action: PositionAction) {
+    with_priority(Priority::High, async {
+        match action {
+            PositionAction::ReplaceStop => replace_stop_order(symbol, order_id, false).await,
+            PositionAction::PartialClose(close_fraction) => {
+                partial_close_position(symbol, order_id, close_fraction).await
+            }
+            PositionAction::Exit => exit_position(symbol, order_id).await,
+        }
+    })
+    .await
+}
+
+// 按仓位最新的止损价和数量重挂止损单，force 为 false 时只处理有待执行请求的仓位
+async fn replace_stop_order(symbol: &str, order_id: u64, force: bool) {
+    let manager = get_position_manager();
+    let mut requested = false;
+    let snapshot = manager
+        .modify_position(symbol, order_id, |t| {
+            requested = t.stop_pending;
+            t.stop_pending = false;
+        })
+        .await;
+    let mut position = match snapshot {
+        Some(position) => position,
+        None => return,
+    };
+    if !(requested || force) || position.status != PositionStatus::Open {
+        return;
+    }
+
+    position.place_stop_order().await;
+    let stop_order = position.stop_order;
+    let updated = manager
+        .modify_position(symbol, order_id, |t| t.stop_order = stop_order)
+        .await;
+    match updated {
+        Some(updated) => save_position(updated),
+        // 挂单期间仓位已被移除，撤掉刚挂出的止损单
+        None => position.cancel_stop_order().await,
+    }
+}
+
+async fn partial_close_position(symbol: &str, order_id: u64, close_fraction: Decimal) {
+    let manager = get_position_manager();
+    let mut position = match manager.modify_position(symbol, order_id, |_| {}).await {
+        Some(position) => position,
+        None => return,
+    };
+    if position.status != PositionStatus::Open {
+        return;
+    }
+    if !position.take_partial_profit(close_fraction).await {
+        return;
+    }
+
+    let closed = position.is_closed();
+    let updated = manager
+        .modify_position(symbol, order_id, |t| {
+            t.quantity = position.quantity.clone();
+            t.stop_order = position.stop_order;
+            if closed {
+                t.status = PositionStatus::Closed;
+            }
+        })
+        .await;
+    if let Some(updated) = updated {
+        save_position(updated);
+    }
+    if !closed {
+        // 按剩余数量重挂止损单
+        replace_stop_order(symbol, order_id, true).await;
+    }
+}
+
+async fn exit_position(symbol: &str, order_id: u64) {
+    let manager = get_position_manager();
+    let mut position = match manager.modify_position(symbol, order_id, |_| {}).await {
+        Some(position) => position,
+        None => return,
+    };
+    if position.status != PositionStatus::ExitPending {
+        return;
+    }
+
+    position.execute_exit().await;
+    let updated = manager
+        .modify_position(symbol, order_id, |t| {
+            t.status = position.status;
+            t.exit_failed_at = position.exit_failed_at;
+            t.exit_attempts = position.exit_attempts;
+            t.stop_order = position.stop_order;
+        })
+        .await;
+    if let Some(updated) = updated {
+        save_position(updated);
+    }
+}
+
 pub async fn clear_sombol_position(symbol: &str) {
     get_position_manager().clear_position(symbol).await;
 }
@@ -324,16 +1239,94 @@ pub async fn update_symbol_position_price(symbol: &str, price: (String, String))
         .await;
 }
 
+// 用户某个交易对某个方向的全部仓位
 pub async fn get_user_symbol_direction_positions(
     symbol: &str,
     direction: &Direction,
     user_id: &str,
-) -> Option<Position> {
+) -> Vec<Position> {
     get_position_manager()
         .get_user_symbol_direction_positions(symbol, direction, user_id)
         .await
 }
 
+// 修改用户运行中的仓位，order_id 为 None 时修改该方向的全部仓位，返回修改后的仓位
+pub async fn update_user_positions(
+    symbol: &str,
+    user_id: &str,
+    direction: &Direction,
+    order_id: Option<u64>,
+    update: PositionUpdate,
+    market_price: Decimal,
+) -> Result<Vec<Position>> {
+    let positions = get_position_manager()
+        .update_user_positions(
+            &symbol.to_lowercase(),
+            user_id,
+            direction,
+            order_id,
+            update,
+            market_price,
+        )
+        .await?;
+    for position in positions.iter() {
+        save_position(position.clone());
+    }
+    Ok(positions)
+}
+
+pub async fn claim_user_position_exit(
+    symbol: &str,
+    user_id: &str,
+    direction: &Direction,
+    order_id: u64,
+) -> Result<Position> {
+    get_position_manager()
+        .claim_user_position_exit(&symbol.to_lowercase(), user_id, direction, order_id)
+        .await
+}
+
+// 手动平仓失败时恢复仓位原来的状态
+pub async fn release_position_exit(symbol: &str, order_id: u64, status: PositionStatus) {
+    get_position_manager()
+        .modify_position(&symbol.to_lowercase(), order_id, |t| t.status = status)
+        .await;
+}
+
+// 移除已手动平仓的仓位并撤销其止损单
+pub async fn remove_user_position(symbol: &str, order_id: u64) {
+    let removed = get_position_manager()
+        .remove_position(&symbol.to_lowercase(), order_id)
+        .await;
+    if let Some(mut closed) = removed {
+        closed.cancel_stop_order().await;
+        closed.status = PositionStatus::Closed;
+        save_position(closed);
+    }
+}
+
+pub async fn check_time_exits(now: i64, funding_times: &HashMap<String, i64>) {
+    get_position_manager()
+        .check_time_exits(now, funding_times)
+        .await;
+}
+
+pub async fn get_all_positions() -> Vec<Position> {
+    get_position_manager().get_all_positions().await
+}
+
+pub async fn reconcile_position(
+    symbol: &str,
+    order_id: u64,
+    quantity: Option<String>,
+) -> Option<Position> {
+    let position = get_position_manager()
+        .reconcile_position(symbol, order_id, quantity)
+        .await?;
+    save_position(position.clone());
+    Some(position)
+}
+
 pub async fn remove_user_symbol_direction_position(
     symbol: &str,
     user_id: &str,
@@ -346,95 +1339,121 @@ pub async fn remove_user_symbol_direction_position(
 
 #[cfg(test)]
 mod tests {
-    use crate::static_items::position::{Direction, Position};
+    use crate::biance::signed::KeyType;
+    use crate::static_items::{
+        position::{Direction, Position},
+        stop_policy::{FixedPercentStop, TieredStop, DEFAULT_TRAIL_FROM},
+        strategy::Strategy,
+    };
 
     use super::*;
     // use std::f64::EPSILON;
-    const EPSILON: f64 = 1e-5;
+    const EPSILON: Decimal = Decimal::from_parts(1, 0, 0, false, 5);
+
+    fn d(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    // 测试仓位：入场价 100、10 倍杠杆、止损 95，测试只覆盖相关的字段，新增字段时不需要修改各个测试
+    fn position(direction: Direction, stop_policy: StopPolicyKind) -> Position {
+        Position {
+            order_id: 1,
+            symbol: "btcusdt".to_string(),
+            ..Position::simulated(direction, d("100"), d("10"), d("0.5"), stop_policy)
+        }
+    }
 
     #[test]
     fn test_calculate_new_stop_loss_long() {
         let strategies = vec![
             Strategy {
-                max: 0.1,
-                adjustment: 0.02,
+                max: d("0.1"),
+                adjustment: d("0.02"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.2,
-                adjustment: 0.04,
+                max: d("0.2"),
+                adjustment: d("0.04"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.3,
-                adjustment: 0.09,
+                max: d("0.3"),
+                adjustment: d("0.09"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.4,
-                adjustment: 0.16,
+                max: d("0.4"),
+                adjustment: d("0.16"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.5,
-                adjustment: 0.25,
+                max: d("0.5"),
+                adjustment: d("0.25"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.6,
-                adjustment: 0.36,
+                max: d("0.6"),
+                adjustment: d("0.36"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.7,
-                adjustment: 0.49,
+                max: d("0.7"),
+                adjustment: d("0.49"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.8,
-                adjustment: 0.64,
+                max: d("0.8"),
+                adjustment: d("0.64"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.9,
-                adjustment: 0.81,
+                max: d("0.9"),
+                adjustment: d("0.81"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 1.0,
-                adjustment: 0.90,
+                max: d("1.0"),
+                adjustment: d("0.90"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 1.1,
-                adjustment: 0.1,
+                max: d("1.1"),
+                adjustment: d("0.1"),
+                close_fraction: Decimal::ZERO,
             },
         ];
         let mut trade = Position {
-            user_id: "".to_string(),
-            entry_price: 4.5,
-            highest_price: 5.0,
-            lowest_price: 4.0,
-            leverage: 10.0,
-            stop_loss: 4.0,
-            order_id: 1,
+            entry_price: d("4.5"),
+            highest_price: d("5.0"),
+            lowest_price: d("4.0"),
+            stop_loss: d("4.0"),
             stop_order: 1,
-            symbol: "Filusdt".to_string(),
-            direction: Direction::Long,
-            quantity: "1.0".to_string(),
-            strategies: strategies.clone(),
-            is_closed: false,
-            api_key: "".to_string(),
-            api_secret: "".to_string(),
+            ..position(
+                Direction::Long,
+                StopPolicyKind::Tiered(TieredStop {
+                    strategies: strategies.clone(),
+                    trail_from: DEFAULT_TRAIL_FROM,
+                }),
+            )
         };
 
         let test_cases = vec![
-            (0.009, 4.0, "No change for profit < 10%"),
-            (0.01, 4.509, "Profit 10%"),
-            (0.02, 4.518, "Profit 20%"),
-            (0.03, 4.5405, "Profit 30%"),
-            (0.04, 4.572, "Profit 40%"),
-            (0.05, 4.6125, "Profit 50%"),
-            (0.06, 4.662, "Profit 60%"),
-            (0.07, 4.7205, "Profit 70%"),
-            (0.08, 4.788, "Profit 80%"),
-            (0.091, 4.8645, "Profit 90%"),
-            (0.1, 4.905, "Profit 100%"),
-            (0.12, 4.95, "Profit 120%"),
+            (d("0.009"), d("4.0"), "No change for profit < 10%"),
+            (d("0.01"), d("4.509"), "Profit 10%"),
+            (d("0.02"), d("4.518"), "Profit 20%"),
+            (d("0.03"), d("4.5405"), "Profit 30%"),
+            (d("0.04"), d("4.572"), "Profit 40%"),
+            (d("0.05"), d("4.6125"), "Profit 50%"),
+            (d("0.06"), d("4.662"), "Profit 60%"),
+            (d("0.07"), d("4.7205"), "Profit 70%"),
+            (d("0.08"), d("4.788"), "Profit 80%"),
+            (d("0.091"), d("4.8645"), "Profit 90%"),
+            (d("0.1"), d("4.905"), "Profit 100%"),
+            (d("0.12"), d("4.95"), "Profit 120%"),
         ];
 
         for (profit, expected, description) in test_cases {
-            let result = trade.calculate_new_stop_loss(profit, true);
+            let (result, _) = trade.calculate_new_stop_loss(profit, true);
             // let EPSILON = 1e-9;
             assert!(
                 (result - expected).abs() <= EPSILON,
@@ -450,85 +1469,93 @@ mod tests {
     fn test_calculate_new_stop_loss_short() {
         let strategies = vec![
             Strategy {
-                max: 0.1,
-                adjustment: 0.02,
+                max: d("0.1"),
+                adjustment: d("0.02"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.2,
-                adjustment: 0.04,
+                max: d("0.2"),
+                adjustment: d("0.04"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.3,
-                adjustment: 0.09,
+                max: d("0.3"),
+                adjustment: d("0.09"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.4,
-                adjustment: 0.16,
+                max: d("0.4"),
+                adjustment: d("0.16"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.5,
-                adjustment: 0.25,
+                max: d("0.5"),
+                adjustment: d("0.25"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.6,
-                adjustment: 0.36,
+                max: d("0.6"),
+                adjustment: d("0.36"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.7,
-                adjustment: 0.49,
+                max: d("0.7"),
+                adjustment: d("0.49"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.8,
-                adjustment: 0.64,
+                max: d("0.8"),
+                adjustment: d("0.64"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 0.9,
-                adjustment: 0.81,
+                max: d("0.9"),
+                adjustment: d("0.81"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 1.0,
-                adjustment: 0.90,
+                max: d("1.0"),
+                adjustment: d("0.90"),
+                close_fraction: Decimal::ZERO,
             },
             Strategy {
-                max: 1.1,
-                adjustment: 0.1,
+                max: d("1.1"),
+                adjustment: d("0.1"),
+                close_fraction: Decimal::ZERO,
             },
         ];
         let mut trade = Position {
-            user_id: "".to_string(),
-            entry_price: 4.5,
-            highest_price: 5.0,
-            lowest_price: 4.0,
-            leverage: 10.0,
-            stop_loss: 5.0,
-            order_id: 1,
+            entry_price: d("4.5"),
+            highest_price: d("5.0"),
+            lowest_price: d("4.0"),
+            stop_loss: d("5.0"),
             stop_order: 1,
-            symbol: "Filusdt".to_string(),
-            direction: Direction::Short,
-            quantity: "1.0".to_string(),
-            strategies,
-            is_closed: false,
-            api_key: "".to_string(),
-            api_secret: "".to_string(),
+            ..position(
+                Direction::Short,
+                StopPolicyKind::Tiered(TieredStop {
+                    strategies,
+                    trail_from: DEFAULT_TRAIL_FROM,
+                }),
+            )
         };
 
         let test_cases = vec![
-            (0.009, 5.0, "No change for profit < 10%"),
-            (0.011, 4.491, "Profit 10%"),
-            (0.021, 4.482, "Profit 20%"),
-            (0.031, 4.4595, "Profit 30%"),
-            (0.041, 4.428, "Profit 40%"),
-            (0.051, 4.3875, "Profit 50%"),
-            (0.061, 4.338, "Profit 60%"),
-            (0.071, 4.2795, "Profit 70%"),
-            (0.081, 4.212, "Profit 80%"),
-            (0.091, 4.1355, "Profit 90%"),
-            (0.10, 4.095, "Profit 100%"),
-            (0.12, 4.04, "Profit 120%"),
+            (d("0.009"), d("5.0"), "No change for profit < 10%"),
+            (d("0.011"), d("4.491"), "Profit 10%"),
+            (d("0.021"), d("4.482"), "Profit 20%"),
+            (d("0.031"), d("4.4595"), "Profit 30%"),
+            (d("0.041"), d("4.428"), "Profit 40%"),
+            (d("0.051"), d("4.3875"), "Profit 50%"),
+            (d("0.061"), d("4.338"), "Profit 60%"),
+            (d("0.071"), d("4.2795"), "Profit 70%"),
+            (d("0.081"), d("4.212"), "Profit 80%"),
+            (d("0.091"), d("4.1355"), "Profit 90%"),
+            (d("0.10"), d("4.095"), "Profit 100%"),
+            (d("0.12"), d("4.04"), "Profit 120%"),
         ];
 
         for (profit, expected, description) in test_cases {
-            let result = trade.calculate_new_stop_loss(profit, false);
+            let (result, _) = trade.calculate_new_stop_loss(profit, false);
             assert!(
                 (result - expected).abs() < EPSILON,
                 "{}: Expected {:.4}, got {:.4}",
@@ -538,4 +1565,277 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_apply_update() {
+        let tier = |max: &str, adjustment: &str| Strategy {
+            max: d(max),
+            adjustment: d(adjustment),
+            close_fraction: Decimal::ZERO,
+        };
+        let mut trade = Position {
+            highest_price: d("102"),
+            stop_loss: d("95"),
+            stop_order: 1,
+            tick_size: d("0.1"),
+            ..position(
+                Direction::Long,
+                StopPolicyKind::Tiered(TieredStop::new(vec![tier("0.1", "0.02")])),
+            )
+        };
+        let stop = |stop_loss: &str, force: bool| PositionUpdate {
+            stop_loss: Some(d(stop_loss)),
+            force,
+            ..Default::default()
+        };
+        let code = |r: Result<bool>| match r {
+            Err(Error::ErrorCode(code)) => code,
+            r => panic!("unexpected result: {:?}", r),
+        };
+
+        // 收紧止损并按 tick 取整
+        assert!(trade.apply_update(stop("97.04", false), d("101")).unwrap());
+        assert_eq!(trade.stop_loss, d("97"));
+        // 已有待执行的重挂请求时不再重复投递
+        assert!(!trade.apply_update(stop("98", false), d("101")).unwrap());
+        assert_eq!(trade.stop_loss, d("98"));
+
+        // 放宽止损需要 force
+        assert_eq!(
+            code(trade.apply_update(stop("96", false), d("101"))),
+            error_code::STOP_LOSS_LOOSENED.0
+        );
+        assert_eq!(trade.stop_loss, d("98"));
+        trade.apply_update(stop("96", true), d("101")).unwrap();
+        assert_eq!(trade.stop_loss, d("96"));
+
+        // 止损价不能越过当前价格
+        assert_eq!(
+            code(trade.apply_update(stop("101", false), d("101"))),
+            error_code::INVALID_STOP_LOSS.0
+        );
+
+        // 替换档位时丢弃已达到的档位（当前收益率 20%）
+        let update = PositionUpdate {
+            strategies: Some(vec![tier("0.1", "0.02"), tier("0.3", "0.1")]),
+            strategy_version_id: Some("v2".to_string()),
+            ..Default::default()
+        };
+        trade.apply_update(update, d("101")).unwrap();
+        assert_eq!(trade.strategy_version_id.as_deref(), Some("v2"));
+        let maxes: Vec<Decimal> = trade
+            .stop_policy
+            .remaining_strategies()
+            .iter()
+            .map(|s| s.max)
+            .collect();
+        assert_eq!(maxes, vec![d("0.3"), d("1.1")]);
+
+        // 非阶梯止损不能只替换档位
+        let update = PositionUpdate {
+            stop_policy: Some(StopPolicyConfig::FixedPercent { percent: d("0.05") }),
+            ..Default::default()
+        };
+        trade.apply_update(update, d("101")).unwrap();
+        // 不再使用用户策略的档位
+        assert_eq!(trade.strategy_version_id, None);
+        let update = PositionUpdate {
+            strategies: Some(vec![tier("0.3", "0.1")]),
+            ..Default::default()
+        };
+        assert_eq!(
+            code(trade.apply_update(update, d("101"))),
+            error_code::INVALID_STOP_POLICY.0
+        );
+
+        // 平仓中的仓位不能修改
+        trade.status = PositionStatus::ExitPending;
+        assert_eq!(
+            code(trade.apply_update(stop("97", false), d("101"))),
+            error_code::POSITION_NOT_OPEN.0
+        );
+    }
+
+    #[test]
+    fn test_check_time_exit() {
+        const HOUR: i64 = 3_600_000;
+        let mut trade = Position {
+            exit_rules: ExitRules {
+                max_hold_hours: Some(4),
+                exit_before_funding_secs: Some(300),
+            },
+            opened_at: HOUR,
+            ..position(
+                Direction::Long,
+                StopPolicyKind::Tiered(TieredStop::new(Vec::new())),
+            )
+        };
+
+        // 未到时间
+        assert_eq!(trade.check_time_exit(4 * HOUR, None), None);
+        // 止损已移到保本以上时不按持仓时间平仓
+        trade.stop_loss = d("100.5");
+        assert_eq!(trade.check_time_exit(5 * HOUR, None), None);
+        trade.stop_loss = d("95");
+        assert_eq!(
+            trade.check_time_exit(5 * HOUR, None),
+            Some(PositionAction::Exit)
+        );
+        assert_eq!(trade.status, PositionStatus::ExitPending);
+        // 平仓中不重复触发
+        assert_eq!(trade.check_time_exit(6 * HOUR, None), None);
+
+        trade.status = PositionStatus::Open;
+        trade.exit_rules.max_hold_hours = None;
+        let funding = 3 * HOUR;
+        // 结算窗口之前和结算之后都不触发
+        assert_eq!(
+            trade.check_time_exit(funding - 301_000, Some(funding)),
+            None
+        );
+        assert_eq!(trade.check_time_exit(funding, Some(funding)), None);
+        assert_eq!(
+            trade.check_time_exit(funding - 60_000, Some(funding)),
+            Some(PositionAction::Exit)
+        );
+
+        // 在结算窗口内开的仓位不受影响
+        trade.status = PositionStatus::Open;
+        trade.opened_at = funding - 120_000;
+        assert_eq!(trade.check_time_exit(funding - 60_000, Some(funding)), None);
+    }
+
+    #[test]
+    fn test_break_even_floor() {
+        // 万分之五吃单费率，做多保本价约为入场价上浮 0.1%
+        let long = fee_break_even_price(&Direction::Long, d("100"), d("0.0005"));
+        assert!((long - d("100.10005")).abs() < EPSILON);
+        let short = fee_break_even_price(&Direction::Short, d("100"), d("0.0005"));
+        assert!((short - d("99.90005")).abs() < EPSILON);
+
+        let mut trade = Position {
+            highest_price: d("103"),
+            tick_size: d("0.1"),
+            break_even_price: long,
+            ..position(
+                Direction::Long,
+                StopPolicyKind::Tiered(TieredStop::new(Vec::new())),
+            )
+        };
+
+        // 锁定利润的止损被抬到向上取整后的保本价
+        assert_eq!(trade.apply_break_even_floor(d("100")), d("100.2"));
+        assert_eq!(trade.apply_break_even_floor(d("100.1")), d("100.2"));
+        // 已高于保本价或仍在入场价以下的止损不受影响
+        assert_eq!(trade.apply_break_even_floor(d("101")), d("101"));
+        assert_eq!(trade.apply_break_even_floor(d("99")), d("99"));
+        // 保本价已越过当前最高价时不调整
+        trade.highest_price = d("100.1");
+        assert_eq!(trade.apply_break_even_floor(d("100")), d("100"));
+
+        trade.direction = Direction::Short;
+        trade.lowest_price = d("97");
+        trade.break_even_price = short;
+        assert_eq!(trade.apply_break_even_floor(d("100")), d("99.9"));
+        assert_eq!(trade.apply_break_even_floor(d("99")), d("99"));
+    }
+
+    #[test]
+    fn test_simulate() {
+        let strategies = vec![
+            Strategy {
+                max: d("0.1"),
+                adjustment: d("0.02"),
+                close_fraction: d("0.5"),
+            },
+            Strategy {
+                max: d("0.2"),
+                adjustment: d("0.04"),
+                close_fraction: Decimal::ZERO,
+            },
+        ];
+        let mut trade = Position::simulated(
+            Direction::Long,
+            d("100"),
+            d("10"),
+            d("0.5"),
+            StopPolicyKind::Tiered(TieredStop::new(strategies)),
+        );
+        assert_eq!(trade.stop_loss, d("95"));
+
+        let prices: Vec<Decimal> = ["101", "102", "101.5", "100.3", "99"]
+            .iter()
+            .map(|p| d(p))
+            .collect();
+        let steps = trade.simulate(&prices, 1000);
+        let stops: Vec<(Decimal, Decimal, bool)> = steps
+            .iter()
+            .map(|s| (s.stop_loss, s.remaining, s.exit))
+            .collect();
+        // 触发止损后不再处理后续价格
+        assert_eq!(
+            stops,
+            vec![
+                (d("100.2"), d("0.5"), false),
+                (d("100.4"), d("0.5"), false),
+                (d("100.4"), d("0.5"), false),
+                (d("100.4"), d("0.5"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simulate_full_partial_close() {
+        // 两档各减开仓数量的一半，第二档后全部平仓
+        let strategies = vec![
+            Strategy {
+                max: d("0.1"),
+                adjustment: d("0.02"),
+                close_fraction: d("0.5"),
+            },
+            Strategy {
+                max: d("0.2"),
+                adjustment: d("0.04"),
+                close_fraction: d("0.5"),
+            },
+        ];
+        let mut trade = Position::simulated(
+            Direction::Long,
+            d("100"),
+            d("10"),
+            d("0.5"),
+            StopPolicyKind::Tiered(TieredStop::new(strategies)),
+        );
+        let prices: Vec<Decimal> = ["101", "102", "103"].iter().map(|p| d(p)).collect();
+        let steps = trade.simulate(&prices, 1000);
+        let remaining: Vec<(Decimal, bool)> = steps.iter().map(|s| (s.remaining, s.exit)).collect();
+        assert_eq!(remaining, vec![(d("0.5"), false), (Decimal::ZERO, true)]);
+    }
+
+    #[test]
+    fn test_position_record_excludes_account() {
+        let mut position = Position::simulated(
+            Direction::Long,
+            d("100"),
+            d("10"),
+            d("0.5"),
+            StopPolicyKind::FixedPercent(FixedPercentStop { percent: d("0.5") }),
+        );
+        position.account = SecretKey::new(
+            "u1".to_string(),
+            "user-api-key".to_string(),
+            "user-api-secret".to_string(),
+            KeyType::Hmac,
+            true,
+        );
+
+        // 落库的内容不含 key 和 secret，读回后账户为空，需要重新设置
+        let record = serde_json::to_string(&position).unwrap();
+        assert!(!record.contains("user-api-key"));
+        assert!(!record.contains("user-api-secret"));
+        assert!(!record.contains("api_secret"));
+        let restored: Position = serde_json::from_str(&record).unwrap();
+        assert!(restored.account.api_key.key.is_empty());
+        assert!(!restored.account.paper_trading);
+    }
 }
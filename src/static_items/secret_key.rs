@@ -5,18 +5,49 @@ use std::{
 
 use tokio::sync::Mutex;
 
+use crate::{
+    biance::signed::{ApiKey, KeyType},
+    database::user_db::db_get_user_info,
+    error::Result,
+    models::user_model::User,
+};
+
 static KEY: LazyLock<Arc<KeyManager>> = LazyLock::new(KeyManager::new);
 
-#[derive(Debug, Clone)]
+// 用户的下单账户，API key 在创建时解析一次，仓位和请求共用
+#[derive(Clone, Debug, Default)]
 pub struct SecretKey {
     pub id: String,
-    pub key: String,
-    pub secret: String,
+    pub api_key: ApiKey,
+    pub paper_trading: bool, // 为 true 时订单发往该用户的模拟账户
 }
 
 impl SecretKey {
-    pub fn new(id: String, key: String, secret: String) -> Self {
-        SecretKey { id, key, secret }
+    pub fn new(
+        id: String,
+        key: String,
+        secret: String,
+        key_type: KeyType,
+        paper_trading: bool,
+    ) -> Self {
+        SecretKey {
+            id,
+            api_key: ApiKey::new(&key, &secret, key_type),
+            paper_trading,
+        }
+    }
+}
+
+impl From<&User> for SecretKey {
+    // 纸面交易按用户的 paper_trading 设置路由，与 API key 的内容无关
+    fn from(user: &User) -> Self {
+        SecretKey::new(
+            user.user_id.clone(),
+            user.key.clone(),
+            user.secret.clone(),
+            user.key_type,
+            user.paper_trading,
+        )
     }
 }
 
@@ -68,3 +99,13 @@ pub async fn get_secret_key(user_id: &str) -> Option<SecretKey> {
 pub async fn delete_secret_key(user_id: &str) {
     get_key_manager().delete_key(user_id).await;
 }
+
+// 用户未登录过时从用户表读取并缓存，用于重启后恢复仓位的账户
+pub async fn load_secret_key(user_id: &str) -> Result<SecretKey> {
+    if let Some(key) = get_secret_key(user_id).await {
+        return Ok(key);
+    }
+    let key = SecretKey::from(&db_get_user_info(user_id).await?);
+    insert_secret_key(key.clone()).await;
+    Ok(key)
+}
@@ -0,0 +1,553 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::strategy::Strategy;
+use crate::error::error_code;
+
+// 收益率（已乘杠杆）达到该值后，止损从入场价切换为跟随最高/最低价
+pub const DEFAULT_TRAIL_FROM: Decimal = Decimal::from_parts(109, 0, 0, false, 2);
+
+// 阶梯策略末尾自动追加的档位，配合 DEFAULT_TRAIL_FROM 使用
+pub const DEFAULT_TRAIL_TIER: Strategy = Strategy {
+    max: Decimal::from_parts(11, 0, 0, false, 1),
+    adjustment: Decimal::from_parts(1, 0, 0, false, 1),
+    close_fraction: Decimal::ZERO,
+};
+
+// 计算止损时仓位的当前状态
+pub struct StopContext {
+    pub is_long: bool,
+    pub entry_price: Decimal,
+    pub leverage: Decimal,
+    pub stop_loss: Decimal,
+    pub highest_price: Decimal,
+    pub lowest_price: Decimal,
+    pub profit_percentage: Decimal, // 未乘杠杆的价格变动比例
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StopDecision {
+    pub stop_loss: Decimal,
+    pub close_fraction: Decimal,
+}
+
+impl StopDecision {
+    fn keep(ctx: &StopContext) -> Self {
+        StopDecision {
+            stop_loss: ctx.stop_loss,
+            close_fraction: Decimal::ZERO,
+        }
+    }
+
+    fn stop(stop_loss: Decimal) -> Self {
+        StopDecision {
+            stop_loss,
+            close_fraction: Decimal::ZERO,
+        }
+    }
+}
+
+pub trait StopPolicy {
+    // 每个价格 tick 调用，供需要累积行情数据的策略使用
+    fn observe(&mut self, _price: Decimal, _time: i64) {}
+
+    // 价格创出新高（做多）或新低（做空）时计算新的止损价与减仓比例
+    fn next_stop(&mut self, ctx: &StopContext) -> StopDecision;
+}
+
+// 用户在开仓时选择的止损方式
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StopPolicyConfig {
+    // 使用 strategy_id 对应的阶梯策略
+    #[default]
+    Tiered,
+    // 按固定收益率跟随最高/最低价
+    FixedPercent {
+        #[serde(with = "rust_decimal::serde::float")]
+        #[schema(value_type = f64)]
+        percent: Decimal,
+    },
+    // 最高/最低价减去 multiplier 倍 ATR，ATR 由 bar_secs 秒的 K 线计算
+    Chandelier {
+        #[serde(with = "rust_decimal::serde::float")]
+        #[schema(value_type = f64)]
+        multiplier: Decimal,
+        period: usize,
+        bar_secs: u64,
+    },
+    // 收益率达到 trigger 后把止损移到入场价附近（offset 为锁定的收益率）
+    BreakEven {
+        #[serde(with = "rust_decimal::serde::float")]
+        #[schema(value_type = f64)]
+        trigger: Decimal,
+        #[serde(with = "rust_decimal::serde::float")]
+        #[schema(value_type = f64)]
+        offset: Decimal,
+    },
+}
+
+impl StopPolicyConfig {
+    // 校验参数：FixedPercent 的 percent 在 0 和杠杆之间（止损价在极值价格和 0 之间），
+    // Chandelier 的参数为正，BreakEven 的 trigger 为正且 offset 在 0 和 trigger 之间（止损不越过当前价格）
+    pub fn validate(&self, leverage: Decimal) -> Result<(), (u16, &'static str)> {
+        let valid = match self {
+            StopPolicyConfig::Tiered => true,
+            StopPolicyConfig::FixedPercent { percent } => {
+                *percent > Decimal::ZERO && *percent < leverage
+            }
+            StopPolicyConfig::Chandelier {
+                multiplier,
+                period,
+                bar_secs,
+            } => *multiplier > Decimal::ZERO && *period > 0 && *bar_secs > 0,
+            StopPolicyConfig::BreakEven { trigger, offset } => {
+                *trigger > Decimal::ZERO && *offset >= Decimal::ZERO && offset < trigger
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(error_code::STOP_POLICY_PARAMS)
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StopPolicyKind {
+    Tiered(TieredStop),
+    FixedPercent(FixedPercentStop),
+    Chandelier(ChandelierStop),
+    BreakEven(BreakEvenStop),
+}
+
+impl StopPolicyKind {
+    pub fn from_config(config: StopPolicyConfig, strategies: Vec<Strategy>) -> Self {
+        match config {
+            StopPolicyConfig::Tiered => StopPolicyKind::Tiered(TieredStop::new(strategies)),
+            StopPolicyConfig::FixedPercent { percent } => {
+                StopPolicyKind::FixedPercent(FixedPercentStop { percent })
+            }
+            StopPolicyConfig::Chandelier {
+                multiplier,
+                period,
+                bar_secs,
+            } => StopPolicyKind::Chandelier(ChandelierStop::new(multiplier, period, bar_secs)),
+            StopPolicyConfig::BreakEven { trigger, offset } => {
+                StopPolicyKind::BreakEven(BreakEvenStop {
+                    trigger,
+                    offset,
+                    triggered: false,
+                })
+            }
+        }
+    }
+}
+
+impl StopPolicyKind {
+    // 当前止损方式对应的配置
+    pub fn config(&self) -> StopPolicyConfig {
+        match self {
+            StopPolicyKind::Tiered(_) => StopPolicyConfig::Tiered,
+            StopPolicyKind::FixedPercent(p) => {
+                StopPolicyConfig::FixedPercent { percent: p.percent }
+            }
+            StopPolicyKind::Chandelier(p) => StopPolicyConfig::Chandelier {
+                multiplier: p.multiplier,
+                period: p.period,
+                bar_secs: (p.bar_ms / 1000) as u64,
+            },
+            StopPolicyKind::BreakEven(p) => StopPolicyConfig::BreakEven {
+                trigger: p.trigger,
+                offset: p.offset,
+            },
+        }
+    }
+
+    // 阶梯止损尚未触发的档位，其他方式返回空
+    pub fn remaining_strategies(&self) -> Vec<Strategy> {
+        match self {
+            StopPolicyKind::Tiered(p) => p.strategies.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    // 运行中替换策略时丢弃已经达到的档位，避免下一次新高时一次性触发
+    pub fn skip_reached_tiers(&mut self, percentage: Decimal) {
+        if let StopPolicyKind::Tiered(p) = self {
+            p.strategies.retain(|adj| percentage < adj.max);
+        }
+    }
+}
+
+impl StopPolicy for StopPolicyKind {
+    fn observe(&mut self, price: Decimal, time: i64) {
+        match self {
+            StopPolicyKind::Tiered(p) => p.observe(price, time),
+            StopPolicyKind::FixedPercent(p) => p.observe(price, time),
+            StopPolicyKind::Chandelier(p) => p.observe(price, time),
+            StopPolicyKind::BreakEven(p) => p.observe(price, time),
+        }
+    }
+
+    fn next_stop(&mut self, ctx: &StopContext) -> StopDecision {
+        match self {
+            StopPolicyKind::Tiered(p) => p.next_stop(ctx),
+            StopPolicyKind::FixedPercent(p) => p.next_stop(ctx),
+            StopPolicyKind::Chandelier(p) => p.next_stop(ctx),
+            StopPolicyKind::BreakEven(p) => p.next_stop(ctx),
+        }
+    }
+}
+
+// 阶梯止损：收益率每跨过一个档位，止损移动到入场价加对应调整值
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TieredStop {
+    pub strategies: Vec<Strategy>, // 尚未触发的档位
+    pub trail_from: Decimal,
+}
+
+impl TieredStop {
+    pub fn new(mut strategies: Vec<Strategy>) -> Self {
+        strategies.push(DEFAULT_TRAIL_TIER);
+        TieredStop {
+            strategies,
+            trail_from: DEFAULT_TRAIL_FROM,
+        }
+    }
+}
+
+impl StopPolicy for TieredStop {
+    fn next_stop(&mut self, ctx: &StopContext) -> StopDecision {
+        let actual_price_change_percentage = ctx.profit_percentage * ctx.leverage;
+
+        let (adjustement, close_fraction) =
+            get_adjustment(actual_price_change_percentage, &mut self.strategies);
+
+        if adjustement.is_zero() {
+            return StopDecision {
+                stop_loss: ctx.stop_loss,
+                close_fraction,
+            };
+        }
+
+        let trailing = actual_price_change_percentage >= self.trail_from;
+        let stop_loss = match (ctx.is_long, trailing) {
+            (true, true) => ctx.highest_price * (Decimal::ONE - adjustement / ctx.leverage),
+            (true, false) => ctx.entry_price * (Decimal::ONE + adjustement / ctx.leverage),
+            (false, true) => ctx.lowest_price * (Decimal::ONE + adjustement / ctx.leverage),
+            (false, false) => ctx.entry_price * (Decimal::ONE - adjustement / ctx.leverage),
+        };
+        StopDecision {
+            stop_loss,
+            close_fraction,
+        }
+    }
+}
+
+// 返回 (止损调整值, 减仓比例)，一次跨越多个档位时止损按最高档位调整，
+// 减仓比例为开仓数量的比例，依次累加各档位
+fn get_adjustment(percentage: Decimal, strategies: &mut Vec<Strategy>) -> (Decimal, Decimal) {
+    let crossed = strategies.iter().filter(|adj| percentage >= adj.max);
+    let r = crossed
+        .clone()
+        .max_by_key(|adj| adj.max)
+        .map_or(Decimal::ZERO, |adj| adj.adjustment);
+    let close_fraction: Decimal = crossed.map(|adj| adj.close_fraction).sum();
+    strategies.retain(|adj| percentage < adj.max);
+    (r, close_fraction.min(Decimal::ONE))
+}
+
+// 固定比例跟踪止损，percent 为允许回撤的收益率
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FixedPercentStop {
+    pub percent: Decimal,
+}
+
+impl StopPolicy for FixedPercentStop {
+    fn next_stop(&mut self, ctx: &StopContext) -> StopDecision {
+        if ctx.is_long {
+            StopDecision::stop(ctx.highest_price * (Decimal::ONE - self.percent / ctx.leverage))
+        } else {
+            StopDecision::stop(ctx.lowest_price * (Decimal::ONE + self.percent / ctx.leverage))
+        }
+    }
+}
+
+// 吊灯止损：止损 = 极值价格 ∓ multiplier × ATR
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChandelierStop {
+    pub multiplier: Decimal,
+    pub period: usize,
+    pub bar_ms: i64,
+    #[serde(default)]
+    pub bar: Option<Bar>, // 正在累积的 K 线
+    #[serde(default)]
+    pub prev_close: Option<Decimal>,
+    #[serde(default)]
+    pub atr: Option<Decimal>,
+    #[serde(default)]
+    pub tr_sum: Decimal, // ATR 初始化前累积的真实波幅
+    #[serde(default)]
+    pub samples: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Bar {
+    pub start: i64,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+}
+
+impl ChandelierStop {
+    pub fn new(multiplier: Decimal, period: usize, bar_secs: u64) -> Self {
+        ChandelierStop {
+            multiplier,
+            period: period.max(1),
+            bar_ms: (bar_secs.max(1) * 1000) as i64,
+            bar: None,
+            prev_close: None,
+            atr: None,
+            tr_sum: Decimal::ZERO,
+            samples: 0,
+        }
+    }
+
+    fn close_bar(&mut self, bar: &Bar) {
+        let true_range = match self.prev_close {
+            Some(prev_close) => (bar.high - bar.low)
+                .max((bar.high - prev_close).abs())
+                .max((bar.low - prev_close).abs()),
+            None => bar.high - bar.low,
+        };
+        self.prev_close = Some(bar.close);
+
+        let period = Decimal::from(self.period);
+        self.atr = match self.atr {
+            // Wilder 平滑
+            Some(atr) => Some((atr * (period - Decimal::ONE) + true_range) / period),
+            None => {
+                self.tr_sum += true_range;
+                self.samples += 1;
+                if self.samples >= self.period {
+                    Some(self.tr_sum / period)
+                } else {
+                    None
+                }
+            }
+        };
+    }
+}
+
+impl StopPolicy for ChandelierStop {
+    fn observe(&mut self, price: Decimal, time: i64) {
+        match self.bar.take() {
+            Some(bar) if time - bar.start >= self.bar_ms => {
+                self.close_bar(&bar);
+                self.bar = Some(Bar {
+                    start: time - (time - bar.start) % self.bar_ms,
+                    high: price,
+                    low: price,
+                    close: price,
+                });
+            }
+            Some(mut bar) => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+                self.bar = Some(bar);
+            }
+            None => {
+                self.bar = Some(Bar {
+                    start: time,
+                    high: price,
+                    low: price,
+                    close: price,
+                });
+            }
+        }
+    }
+
+    fn next_stop(&mut self, ctx: &StopContext) -> StopDecision {
+        match self.atr {
+            Some(atr) if ctx.is_long => {
+                StopDecision::stop(ctx.highest_price - self.multiplier * atr)
+            }
+            Some(atr) => StopDecision::stop(ctx.lowest_price + self.multiplier * atr),
+            // ATR 尚未就绪时保持初始止损
+            None => StopDecision::keep(ctx),
+        }
+    }
+}
+
+// 保本止损：收益率达到 trigger 后一次性把止损移到入场价附近
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BreakEvenStop {
+    pub trigger: Decimal,
+    pub offset: Decimal,
+    #[serde(default)]
+    pub triggered: bool,
+}
+
+impl StopPolicy for BreakEvenStop {
+    fn next_stop(&mut self, ctx: &StopContext) -> StopDecision {
+        if self.triggered || ctx.profit_percentage * ctx.leverage < self.trigger {
+            return StopDecision::keep(ctx);
+        }
+        self.triggered = true;
+        if ctx.is_long {
+            StopDecision::stop(ctx.entry_price * (Decimal::ONE + self.offset / ctx.leverage))
+        } else {
+            StopDecision::stop(ctx.entry_price * (Decimal::ONE - self.offset / ctx.leverage))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn context(is_long: bool, extreme: &str, profit_percentage: &str) -> StopContext {
+        StopContext {
+            is_long,
+            entry_price: d("100"),
+            leverage: d("10"),
+            stop_loss: if is_long { d("95") } else { d("105") },
+            highest_price: d(extreme),
+            lowest_price: d(extreme),
+            profit_percentage: d(profit_percentage),
+        }
+    }
+
+    fn tier(max: &str, adjustment: &str, close_fraction: &str) -> Strategy {
+        Strategy {
+            max: d(max),
+            adjustment: d(adjustment),
+            close_fraction: d(close_fraction),
+        }
+    }
+
+    #[test]
+    fn test_get_adjustment_close_fraction() {
+        let mut strategies = vec![
+            tier("0.1", "0.02", "0"),
+            tier("0.2", "0.04", "0.25"),
+            tier("0.3", "0.09", "0.25"),
+        ];
+
+        assert_eq!(
+            get_adjustment(d("0.05"), &mut strategies),
+            (Decimal::ZERO, Decimal::ZERO)
+        );
+        assert_eq!(
+            get_adjustment(d("0.15"), &mut strategies),
+            (d("0.02"), Decimal::ZERO)
+        );
+        // 一次跨越两个档位，减仓比例累加，止损按最高档位调整
+        assert_eq!(
+            get_adjustment(d("0.35"), &mut strategies),
+            (d("0.09"), d("0.5"))
+        );
+        assert!(strategies.is_empty());
+    }
+
+    #[test]
+    fn test_fixed_percent_stop() {
+        let mut policy = FixedPercentStop { percent: d("0.2") };
+        let long = policy.next_stop(&context(true, "110", "0.1"));
+        assert_eq!(long.stop_loss, d("107.8"));
+        let short = policy.next_stop(&context(false, "90", "0.1"));
+        assert_eq!(short.stop_loss, d("91.8"));
+    }
+
+    #[test]
+    fn test_break_even_stop() {
+        let mut policy = BreakEvenStop {
+            trigger: d("0.5"),
+            offset: d("0.01"),
+            triggered: false,
+        };
+        let ctx = context(true, "104", "0.04");
+        assert_eq!(policy.next_stop(&ctx), StopDecision::keep(&ctx));
+
+        let moved = policy.next_stop(&context(true, "106", "0.06"));
+        assert_eq!(moved.stop_loss, d("100.1"));
+
+        // 只移动一次
+        let ctx = context(true, "120", "0.2");
+        assert_eq!(policy.next_stop(&ctx), StopDecision::keep(&ctx));
+    }
+
+    #[test]
+    fn test_chandelier_stop() {
+        let mut policy = ChandelierStop::new(d("2"), 2, 60);
+        let ctx = context(true, "110", "0.1");
+        assert_eq!(policy.next_stop(&ctx), StopDecision::keep(&ctx));
+
+        // 三根 1 分钟 K 线：[100, 102]、[101, 104]、[103, 103]
+        let ticks = [
+            ("100", 0),
+            ("102", 30_000),
+            ("101", 60_000),
+            ("104", 90_000),
+            ("103", 120_000),
+        ];
+        for (price, time) in ticks {
+            policy.observe(d(price), time);
+        }
+
+        // TR1 = 2，TR2 = max(3, |104 - 102|, |101 - 102|) = 3，ATR = 2.5
+        assert_eq!(policy.atr, Some(d("2.5")));
+        let long = policy.next_stop(&ctx);
+        assert_eq!(long.stop_loss, d("105"));
+        let short = policy.next_stop(&context(false, "90", "0.1"));
+        assert_eq!(short.stop_loss, d("95"));
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let leverage = d("10");
+        let fixed = |percent: &str| StopPolicyConfig::FixedPercent {
+            percent: d(percent),
+        };
+        assert!(fixed("0.5").validate(leverage).is_ok());
+        // 为 0 时止损等于极值价格，不小于杠杆时止损价不为正
+        for percent in ["0", "-0.1", "10", "12"] {
+            assert_eq!(
+                fixed(percent).validate(leverage),
+                Err(error_code::STOP_POLICY_PARAMS)
+            );
+        }
+
+        let break_even = |trigger: &str, offset: &str| StopPolicyConfig::BreakEven {
+            trigger: d(trigger),
+            offset: d(offset),
+        };
+        assert!(break_even("0.2", "0").validate(leverage).is_ok());
+        assert!(break_even("0.2", "0.05").validate(leverage).is_ok());
+        assert!(break_even("0", "0").validate(leverage).is_err());
+        assert!(break_even("0.2", "-0.1").validate(leverage).is_err());
+        assert!(break_even("0.2", "0.2").validate(leverage).is_err());
+
+        let chandelier = StopPolicyConfig::Chandelier {
+            multiplier: d("3"),
+            period: 14,
+            bar_secs: 60,
+        };
+        assert!(chandelier.validate(leverage).is_ok());
+        let chandelier = StopPolicyConfig::Chandelier {
+            multiplier: Decimal::ZERO,
+            period: 14,
+            bar_secs: 60,
+        };
+        assert!(chandelier.validate(leverage).is_err());
+        assert!(StopPolicyConfig::Tiered.validate(leverage).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -6,90 +7,200 @@ use std::{
 use tokio::sync::Mutex;
 use utoipa::ToSchema;
 
+use crate::{error::error_code, models::CommonError};
+
 #[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
 pub struct Strategy {
-    pub max: f64,
-    pub adjustment: f64,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub max: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub adjustment: Decimal,
+    #[serde(default, with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub close_fraction: Decimal, // 触发该档位时减仓的数量占开仓数量的比例，0 表示只移动止损
 }
 
-#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
-pub struct StrategyConfig {
-    pub s1: Vec<Strategy>,
-    pub s2: Vec<Strategy>,
-}
-
-impl Default for StrategyConfig {
-    fn default() -> Self {
-        let strategies = vec![
-            Strategy {
-                // min: 0.10,
-                max: 0.10,
-                adjustment: 0.02,
-            },
-            Strategy {
-                // min: 0.20,
-                max: 0.20,
-                adjustment: 0.04,
-            },
-            Strategy {
-                // min: 0.30,
-                max: 0.30,
-                adjustment: 0.09,
-            },
-            Strategy {
-                // min: 0.40,
-                max: 0.40,
-                adjustment: 0.16,
-            },
-            Strategy {
-                // min: 0.50,
-                max: 0.50,
-                adjustment: 0.25,
-            },
-            Strategy {
-                // min: 0.60,
-                max: 0.60,
-                adjustment: 0.36,
-            },
-            Strategy {
-                // min: 0.70,
-                max: 0.70,
-                adjustment: 0.49,
-            },
-            Strategy {
-                // min: 0.7999,
-                max: 0.80,
-                adjustment: 0.64,
-            },
-            Strategy {
-                // min: 0.7999,
-                max: 0.90,
-                adjustment: 0.74,
-            },
-            Strategy {
-                // min: 0.8999,
-                max: 1.0,
-                adjustment: 0.81,
-            },
-        ];
-
-        StrategyConfig {
-            s1: strategies.clone(),
-            s2: strategies,
+// 档位校验失败时的错误码和出错档位（从 0 开始）
+#[derive(Debug, PartialEq)]
+pub struct TierError {
+    pub tier: usize,
+    pub code: (u16, &'static str),
+}
+
+impl From<TierError> for CommonError {
+    fn from(e: TierError) -> Self {
+        CommonError {
+            code: e.code.0,
+            message: format!("{} (tier {})", e.code.1, e.tier),
+        }
+    }
+}
+
+// 校验阶梯档位：max 递增、锁定收益在 0 和 max 之间且逐档不回退、减仓比例合计不超过 1（即不超过开仓数量）
+pub fn validate_tiers(tiers: &[Strategy]) -> Result<(), TierError> {
+    if tiers.is_empty() {
+        return Err(TierError {
+            tier: 0,
+            code: error_code::STRATEGY_EMPTY,
+        });
+    }
+
+    let mut close_total = Decimal::ZERO;
+    for (i, tier) in tiers.iter().enumerate() {
+        let error = |code| Err(TierError { tier: i, code });
+        if tier.max <= Decimal::ZERO
+            || tier.adjustment < Decimal::ZERO
+            || tier.adjustment >= tier.max
+        {
+            return error(error_code::STRATEGY_TIER_RANGE);
+        }
+        if tier.close_fraction < Decimal::ZERO || tier.close_fraction > Decimal::ONE {
+            return error(error_code::STRATEGY_CLOSE_FRACTION);
+        }
+        close_total += tier.close_fraction;
+        if close_total > Decimal::ONE {
+            return error(error_code::STRATEGY_CLOSE_FRACTION);
+        }
+        if let Some(prev) = i.checked_sub(1).map(|p| &tiers[p]) {
+            if tier.max <= prev.max {
+                return error(error_code::STRATEGY_TIER_UNSORTED);
+            }
+            if tier.adjustment < prev.adjustment {
+                return error(error_code::STRATEGY_TIER_REGRESSED);
+            }
         }
     }
+    Ok(())
+}
+
+// 新用户默认创建的阶梯策略
+pub fn default_tiers() -> Vec<Strategy> {
+    vec![
+        Strategy {
+            // min: 0.10,
+            max: Decimal::new(10, 2),
+            adjustment: Decimal::new(2, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.20,
+            max: Decimal::new(20, 2),
+            adjustment: Decimal::new(4, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.30,
+            max: Decimal::new(30, 2),
+            adjustment: Decimal::new(9, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.40,
+            max: Decimal::new(40, 2),
+            adjustment: Decimal::new(16, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.50,
+            max: Decimal::new(50, 2),
+            adjustment: Decimal::new(25, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.60,
+            max: Decimal::new(60, 2),
+            adjustment: Decimal::new(36, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.70,
+            max: Decimal::new(70, 2),
+            adjustment: Decimal::new(49, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.7999,
+            max: Decimal::new(80, 2),
+            adjustment: Decimal::new(64, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.7999,
+            max: Decimal::new(90, 2),
+            adjustment: Decimal::new(74, 2),
+            close_fraction: Decimal::ZERO,
+        },
+        Strategy {
+            // min: 0.8999,
+            max: Decimal::new(10, 1),
+            adjustment: Decimal::new(81, 2),
+            close_fraction: Decimal::ZERO,
+        },
+    ]
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+// 用户保存的命名策略
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
 pub struct UserStrategy {
+    pub strategy_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub tiers: Vec<Strategy>,
+    #[serde(default)]
+    pub version: u32, // 当前版本号，从 1 开始
+    #[serde(default)]
+    pub version_id: String, // 当前版本的 ID
+}
+
+impl UserStrategy {
+    pub fn new(user_id: &str, name: String, tiers: Vec<Strategy>) -> Self {
+        UserStrategy {
+            strategy_id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            name,
+            tiers,
+            version: 1,
+            version_id: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    // 修改名称或档位后生成新的版本号
+    pub fn next_version(&mut self) {
+        self.version += 1;
+        self.version_id = uuid::Uuid::new_v4().to_string();
+    }
+}
+
+// 策略每次修改保存的不可变快照
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct StrategyVersion {
+    pub version_id: String,
+    pub strategy_id: String,
     pub user_id: String,
-    pub cfg: StrategyConfig,
+    pub version: u32,
+    pub name: String,
+    pub tiers: Vec<Strategy>,
+}
+
+impl From<&UserStrategy> for StrategyVersion {
+    fn from(strategy: &UserStrategy) -> Self {
+        StrategyVersion {
+            version_id: strategy.version_id.clone(),
+            strategy_id: strategy.strategy_id.clone(),
+            user_id: strategy.user_id.clone(),
+            version: strategy.version,
+            name: strategy.name.clone(),
+            tiers: strategy.tiers.clone(),
+        }
+    }
 }
 
 static STRATEGY: LazyLock<Arc<StrategyManager>> = LazyLock::new(StrategyManager::new);
 
 pub struct StrategyManager {
-    keys: Mutex<HashMap<String, UserStrategy>>, // 锁住 HashMap，确保多线程安全
+    keys: Mutex<HashMap<String, Vec<UserStrategy>>>, // 锁住 HashMap，确保多线程安全
 }
 
 impl StrategyManager {
@@ -100,26 +211,47 @@ impl StrategyManager {
         })
     }
 
-    pub async fn insert_strategy(&self, strategy: UserStrategy) {
+    pub async fn insert_strategies(&self, user_id: &str, strategies: Vec<UserStrategy>) {
         let mut map = self.keys.lock().await;
-        map.insert(strategy.user_id.clone(), strategy);
+        map.insert(user_id.to_string(), strategies);
     }
 
-    pub async fn update_strategy(&self, user_id: &str, cfg: StrategyConfig) {
+    // 新增或替换同一 strategy_id 的策略
+    pub async fn upsert_strategy(&self, strategy: UserStrategy) {
         let mut map = self.keys.lock().await;
-        if let Some(strategy) = map.get_mut(user_id) {
-            strategy.cfg = cfg;
+        let strategies = map.entry(strategy.user_id.clone()).or_default();
+        match strategies
+            .iter_mut()
+            .find(|s| s.strategy_id == strategy.strategy_id)
+        {
+            Some(s) => *s = strategy,
+            None => strategies.push(strategy),
         }
     }
+
+    pub async fn remove_strategy(&self, user_id: &str, strategy_id: &str) {
+        let mut map = self.keys.lock().await;
+        if let Some(strategies) = map.get_mut(user_id) {
+            strategies.retain(|s| s.strategy_id != strategy_id);
+        }
+    }
+
     pub async fn delete_strategy(&self, user_id: &str) {
         let mut map = self.keys.lock().await;
         map.remove(user_id);
     }
 
-    pub async fn get_strategy(&self, user_id: &str) -> Option<StrategyConfig> {
+    pub async fn get_strategies(&self, user_id: &str) -> Option<Vec<UserStrategy>> {
+        let map = self.keys.lock().await;
+        map.get(user_id).cloned()
+    }
+
+    pub async fn get_strategy(&self, user_id: &str, strategy_id: &str) -> Option<UserStrategy> {
         let map = self.keys.lock().await;
-        let key = map.get(user_id).cloned();
-        key.map(|k| k.cfg)
+        map.get(user_id)?
+            .iter()
+            .find(|s| s.strategy_id == strategy_id)
+            .cloned()
     }
 }
 
@@ -127,32 +259,89 @@ fn get_strategy_manager() -> Arc<StrategyManager> {
     STRATEGY.clone()
 }
 
-pub async fn insert_user_strategy(strategy: UserStrategy) {
-    get_strategy_manager().insert_strategy(strategy).await;
+pub async fn insert_user_strategies(user_id: &str, strategies: Vec<UserStrategy>) {
+    get_strategy_manager()
+        .insert_strategies(user_id, strategies)
+        .await;
+}
+
+pub async fn upsert_user_strategy(strategy: UserStrategy) {
+    get_strategy_manager().upsert_strategy(strategy).await;
 }
 
-pub async fn update_user_strategy(user_id: &str, cfg: StrategyConfig) {
-    get_strategy_manager().update_strategy(user_id, cfg).await;
+pub async fn remove_user_strategy(user_id: &str, strategy_id: &str) {
+    get_strategy_manager()
+        .remove_strategy(user_id, strategy_id)
+        .await;
 }
 
 pub async fn delete_user_strategy(user_id: &str) {
     get_strategy_manager().delete_strategy(user_id).await;
 }
 
-pub async fn get_user_strategy(user_id: &str) -> Option<StrategyConfig> {
-    get_strategy_manager().get_strategy(user_id).await
+pub async fn get_user_strategies(user_id: &str) -> Option<Vec<UserStrategy>> {
+    get_strategy_manager().get_strategies(user_id).await
 }
 
-pub async fn get_user_spec_strategy(user_id: &str, spec_id: u8) -> Option<Vec<Strategy>> {
-    let cfg = get_user_strategy(user_id).await;
-    match cfg {
-        Some(cfg) => {
-            if spec_id == 1 {
-                Some(cfg.s1)
-            } else {
-                Some(cfg.s2)
-            }
+pub async fn get_user_strategy(user_id: &str, strategy_id: &str) -> Option<UserStrategy> {
+    get_strategy_manager()
+        .get_strategy(user_id, strategy_id)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{default_tiers, validate_tiers, Strategy};
+    use crate::error::error_code;
+
+    fn tier(max: i64, adjustment: i64, close_fraction: i64) -> Strategy {
+        Strategy {
+            max: Decimal::new(max, 2),
+            adjustment: Decimal::new(adjustment, 2),
+            close_fraction: Decimal::new(close_fraction, 2),
         }
-        None => None,
+    }
+
+    fn invalid_tier(tiers: &[Strategy]) -> Option<(usize, u16)> {
+        validate_tiers(tiers).err().map(|e| (e.tier, e.code.0))
+    }
+
+    #[test]
+    fn test_validate_tiers() {
+        assert!(validate_tiers(&default_tiers()).is_ok());
+        // 减仓比例按开仓数量计算，两档各平一半后全部平仓
+        assert!(validate_tiers(&[tier(10, 2, 50), tier(20, 2, 50)]).is_ok());
+
+        assert_eq!(invalid_tier(&[]), Some((0, error_code::STRATEGY_EMPTY.0)));
+        // max 未递增
+        assert_eq!(
+            invalid_tier(&[tier(10, 2, 0), tier(30, 4, 0), tier(20, 9, 0)]),
+            Some((2, error_code::STRATEGY_TIER_UNSORTED.0))
+        );
+        // 锁定收益为负或不小于阈值
+        assert_eq!(
+            invalid_tier(&[tier(10, -1, 0)]),
+            Some((0, error_code::STRATEGY_TIER_RANGE.0))
+        );
+        assert_eq!(
+            invalid_tier(&[tier(10, 2, 0), tier(20, 20, 0)]),
+            Some((1, error_code::STRATEGY_TIER_RANGE.0))
+        );
+        // 止损回退
+        assert_eq!(
+            invalid_tier(&[tier(10, 5, 0), tier(20, 4, 0)]),
+            Some((1, error_code::STRATEGY_TIER_REGRESSED.0))
+        );
+        // 减仓比例越界或合计超过 1
+        assert_eq!(
+            invalid_tier(&[tier(10, 2, 120)]),
+            Some((0, error_code::STRATEGY_CLOSE_FRACTION.0))
+        );
+        assert_eq!(
+            invalid_tier(&[tier(10, 2, 60), tier(20, 4, 60)]),
+            Some((1, error_code::STRATEGY_CLOSE_FRACTION.0))
+        );
     }
 }
@@ -0,0 +1,172 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::Instant,
+};
+
+use tokio::time::{self, Duration};
+
+use crate::static_items::position::{execute_position_action, PositionAction};
+
+// 从收到行情到开始下单超过该时间时打印告警
+const EXECUTION_LATENCY_WARN: Duration = Duration::from_millis(100);
+// 延迟统计输出间隔
+const STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+// 价格线程投递给执行线程的交易所操作
+#[derive(Debug)]
+pub struct ExecutionJob {
+    pub symbol: String,
+    pub order_id: u64,
+    pub action: PositionAction,
+    pub triggered_at: Instant, // 触发该操作的行情处理时间
+}
+
+static EXECUTOR: LazyLock<Executor> = LazyLock::new(Executor::default);
+
+// 每个仓位一个任务按顺序执行自己的操作，不同仓位的操作并发执行，单个仓位重试时不影响其他仓位
+#[derive(Default)]
+struct Executor {
+    queues: Mutex<HashMap<u64, VecDeque<ExecutionJob>>>, // 有执行任务的仓位及其待执行的操作
+    stats: ExecutionStats,
+}
+
+impl Executor {
+    // 仓位已有执行任务时排队，否则返回该操作由调用方启动新任务
+    fn enqueue(&self, job: ExecutionJob) -> Option<ExecutionJob> {
+        let mut queues = self.queues.lock().unwrap();
+        match queues.get_mut(&job.order_id) {
+            Some(queue) => {
+                queue.push_back(job);
+                None
+            }
+            None => {
+                queues.insert(job.order_id, VecDeque::new());
+                Some(job)
+            }
+        }
+    }
+
+    // 取出该仓位的下一个操作，队列为空时结束该仓位的执行任务
+    fn next(&self, order_id: u64) -> Option<ExecutionJob> {
+        let mut queues = self.queues.lock().unwrap();
+        let job = queues
+            .get_mut(&order_id)
+            .and_then(|queue| queue.pop_front());
+        if job.is_none() {
+            queues.remove(&order_id);
+        }
+        job
+    }
+}
+
+// 行情到下单的延迟统计，单位微秒
+#[derive(Default)]
+struct ExecutionStats {
+    jobs: AtomicU64,
+    total_wait: AtomicU64,
+    max_wait: AtomicU64,
+    max_execute: AtomicU64,
+}
+
+impl ExecutionStats {
+    fn record(&self, wait: Duration, execute: Duration) {
+        let wait = wait.as_micros() as u64;
+        let execute = execute.as_micros() as u64;
+        self.jobs.fetch_add(1, Ordering::Relaxed);
+        self.total_wait.fetch_add(wait, Ordering::Relaxed);
+        self.max_wait.fetch_max(wait, Ordering::Relaxed);
+        self.max_execute.fetch_max(execute, Ordering::Relaxed);
+    }
+
+    // 输出并清零本周期的统计
+    fn report(&self) {
+        let jobs = self.jobs.swap(0, Ordering::Relaxed);
+        let total_wait = self.total_wait.swap(0, Ordering::Relaxed);
+        let max_wait = self.max_wait.swap(0, Ordering::Relaxed);
+        let max_execute = self.max_execute.swap(0, Ordering::Relaxed);
+        if jobs == 0 {
+            return;
+        }
+        println!(
+            "execution stats: jobs {}, avg wait {}us, max wait {}us, max execute {}us",
+            jobs,
+            total_wait / jobs,
+            max_wait,
+            max_execute
+        );
+    }
+}
+
+// 投递交易所操作，不等待执行结果
+pub fn submit_job(job: ExecutionJob) {
+    if let Some(job) = EXECUTOR.enqueue(job) {
+        tokio::spawn(run_position_jobs(job));
+    }
+}
+
+// 定期输出延迟统计
+pub async fn start_executor() {
+    let mut interval = time::interval(STATS_INTERVAL);
+    loop {
+        interval.tick().await;
+        EXECUTOR.stats.report();
+    }
+}
+
+async fn run_position_jobs(job: ExecutionJob) {
+    let order_id = job.order_id;
+    let mut next = Some(job);
+    while let Some(job) = next {
+        let wait = job.triggered_at.elapsed();
+        if wait > EXECUTION_LATENCY_WARN {
+            eprintln!(
+                "execution of {:?} for {} delayed {}ms",
+                job.action,
+                job.order_id,
+                wait.as_millis()
+            );
+        }
+
+        let started = Instant::now();
+        execute_position_action(&job.symbol, job.order_id, job.action).await;
+        EXECUTOR.stats.record(wait, started.elapsed());
+        next = EXECUTOR.next(order_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(order_id: u64, action: PositionAction) -> ExecutionJob {
+        ExecutionJob {
+            symbol: "btcusdt".to_owned(),
+            order_id,
+            action,
+            triggered_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_position_queues() {
+        let executor = Executor::default();
+        assert!(executor
+            .enqueue(job(1, PositionAction::ReplaceStop))
+            .is_some());
+        // 同一仓位的操作排在执行中的任务后面，其他仓位立即执行
+        assert!(executor.enqueue(job(1, PositionAction::Exit)).is_none());
+        assert!(executor.enqueue(job(2, PositionAction::Exit)).is_some());
+
+        let next = executor.next(1).unwrap();
+        assert!(matches!(next.action, PositionAction::Exit));
+        assert!(executor.next(1).is_none());
+        // 队列清空后新的操作重新启动任务
+        assert!(executor
+            .enqueue(job(1, PositionAction::ReplaceStop))
+            .is_some());
+    }
+}
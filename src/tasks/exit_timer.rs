@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use tokio::time::{self, Duration};
+
+use crate::{biance::market::get_premium_index, static_items::position::check_time_exits};
+
+// 检查按时间平仓规则的间隔
+const EXIT_TIMER_INTERVAL: Duration = Duration::from_secs(5);
+// 资金费结算时间的刷新间隔（毫秒）
+const FUNDING_REFRESH_INTERVAL: i64 = 60_000;
+
+// 定时检查持仓时间和资金费结算时间，与行情驱动的止损检查并行
+pub async fn start_exit_timer() {
+    let mut interval = time::interval(EXIT_TIMER_INTERVAL);
+    let mut funding_times: HashMap<String, i64> = HashMap::new();
+    let mut refreshed_at = 0;
+    loop {
+        interval.tick().await;
+        let now = Utc::now().timestamp_millis();
+
+        if now - refreshed_at >= FUNDING_REFRESH_INTERVAL {
+            match get_premium_index().await {
+                Ok(list) => {
+                    funding_times = list
+                        .into_iter()
+                        .map(|p| (p.symbol.to_lowercase(), p.next_funding_time))
+                        .collect();
+                    refreshed_at = now;
+                }
+                Err(e) => eprintln!("get premium index error: {:?}", e),
+            }
+        }
+
+        check_time_exits(now, &funding_times).await;
+    }
+}
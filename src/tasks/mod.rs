@@ -0,0 +1,4 @@
+pub mod executor;
+pub mod exit_timer;
+pub mod reconciler;
+pub mod time_sync;
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use tokio::time::{self, Duration};
+
+use crate::{
+    database::event_db::db_create_position_event,
+    error::Result,
+    models::event_model::{CreatePositionEventRequest, PositionEventKind},
+    static_items::position::{get_all_positions, reconcile_position, Direction, Position},
+};
+
+// 对账间隔
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+// 定期将本地仓位与币安 positionRisk 对账，清理用户在交易所手动平仓或被强平的仓位
+pub async fn start_reconciler() {
+    let mut interval = time::interval(RECONCILE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let mut users: HashMap<String, Vec<Position>> = HashMap::new();
+        for position in get_all_positions().await {
+            users
+                .entry(position.user_id.clone())
+                .or_default()
+                .push(position);
+        }
+
+        for (user_id, positions) in users {
+            if let Err(e) = reconcile_user(positions).await {
+                eprintln!("reconcile user {} error: {:?}", user_id, e);
+            }
+        }
+    }
+}
+
+async fn reconcile_user(positions: Vec<Position>) -> Result<()> {
+    let risks = match positions.first() {
+        Some(p) => p.exchange().get_risk().await?,
+        None => return Ok(()),
+    };
+
+    let mut groups: HashMap<(String, String), Vec<Position>> = HashMap::new();
+    for position in positions {
+        let position_side = match position.direction {
+            Direction::Long => "LONG",
+            Direction::Short => "SHORT",
+        };
+        groups
+            .entry((position.symbol.clone(), position_side.to_string()))
+            .or_default()
+            .push(position);
+    }
+
+    for ((symbol, position_side), mut lots) in groups {
+        let exchange_amt = risks
+            .iter()
+            .find(|risk| {
+                risk.symbol == symbol.to_uppercase() && risk.position_side == position_side
+            })
+            .map_or(Decimal::ZERO, |risk| risk.position_amt.abs());
+        let tracked_amt: Decimal = lots.iter().map(lot_quantity).sum();
+        if exchange_amt >= tracked_amt {
+            continue;
+        }
+
+        // 交易所持仓少于本地记录，从最新的仓位开始扣减
+        let mut excess = tracked_amt - exchange_amt;
+        lots.sort_by_key(|b| std::cmp::Reverse(b.order_id));
+        for lot in lots {
+            if excess <= Decimal::ZERO {
+                break;
+            }
+            let quantity = lot_quantity(&lot);
+            let (new_quantity, kind) = if excess >= quantity {
+                excess -= quantity;
+                (None, PositionEventKind::ReconcileDropped)
+            } else {
+                let remain = (quantity - excess).normalize().to_string();
+                excess = Decimal::ZERO;
+                (Some(remain), PositionEventKind::ReconcileAdjusted)
+            };
+
+            let detail = format!(
+                "{} {}: tracked {}, exchange {}, lot {} -> {}",
+                symbol,
+                position_side,
+                tracked_amt,
+                exchange_amt,
+                lot.quantity,
+                new_quantity.as_deref().unwrap_or("0"),
+            );
+            println!("reconcile position {}: {}", lot.order_id, detail);
+
+            if reconcile_position(&symbol, lot.order_id, new_quantity)
+                .await
+                .is_none()
+            {
+                continue;
+            }
+            let input = CreatePositionEventRequest {
+                user_id: lot.user_id.clone(),
+                order_id: lot.order_id,
+                symbol: symbol.clone(),
+                kind,
+                detail,
+            };
+            db_create_position_event(input).await?;
+        }
+    }
+    Ok(())
+}
+
+fn lot_quantity(position: &Position) -> Decimal {
+    position.quantity.parse().unwrap_or(Decimal::ZERO)
+}
@@ -0,0 +1,23 @@
+use tokio::time::{self, Duration};
+
+use crate::biance::signed::sync_server_time;
+
+// 同步币安服务器时间的间隔
+const TIME_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+// 时钟偏差超过该值（毫秒）时打印日志
+const TIME_OFFSET_WARNING: i64 = 1000;
+
+// 定期轮询 /fapi/v1/time，签名请求的 timestamp 按偏差校正，避免本地时钟漂移导致 -1021
+pub async fn start_time_sync() {
+    let mut interval = time::interval(TIME_SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        match sync_server_time().await {
+            Ok(offset) if offset.abs() > TIME_OFFSET_WARNING => {
+                println!("binance server time offset: {} ms", offset)
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("sync server time error: {:?}", e),
+        }
+    }
+}
@@ -1,67 +1,97 @@
 use rust_decimal::prelude::ToPrimitive;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::Deserialize;
 
-use crate::biance::biance_trade::get_biance_risk;
-
-use crate::biance::order::{get_biance_active_order, get_biance_finished_order};
+use crate::biance::config::exchange_config;
+use crate::biance::exchange::{Exchange, OrderRequest};
 use crate::database::fee_db::db_create_fee;
 use crate::error::{Error, Result};
-use crate::models::biance_model::{BiannceOrder, Risk, TradeRecord};
+use crate::models::biance_model::{ActiveOrder, BiannceOrder, Risk, TradeRecord};
 use crate::models::fee_model::CreateFeeRequest;
+use crate::models::trade_model::CreatePositionRequest;
 use crate::static_items::user_info::get_agent_id;
-use crate::{biance::order::create_biance_order, models::trade_model::CreatePositionRequest};
 
 pub fn calculate_quantity(
     trade_request: &CreatePositionRequest,
-    market_price: f64,
+    market_price: Decimal,
     precision: u8,
 ) -> String {
     // 确保市场价格有效，避免除以 0
-    if market_price <= 0.0 {
+    if market_price <= Decimal::ZERO {
         return "0.0".to_string();
     }
 
-    let leverage_f64 = trade_request.leverage as f64;
+    let leverage = Decimal::from(trade_request.leverage);
     // 计算可买数量
-    let quantity = trade_request.margin * leverage_f64 / market_price;
-    // 动态格式化数量，保留指定的精度
-    format!("{:.precision$}", quantity, precision = precision as usize)
+    let quantity = trade_request.margin * leverage / market_price;
+    // 按精度向下取整，避免超出保证金
+    quantity
+        .round_dp_with_strategy(precision as u32, RoundingStrategy::ToZero)
+        .normalize()
+        .to_string()
+}
+
+// 将价格取整到最近的 tick，tick_size 为 0 时原样返回
+pub fn round_to_tick(price: Decimal, tick_size: Decimal) -> Decimal {
+    if tick_size <= Decimal::ZERO {
+        return price;
+    }
+    ((price / tick_size).round() * tick_size).normalize()
 }
 
 pub async fn create_position_order(
+    exchange: &dyn Exchange,
     symbol: &str,
     side: &str,
     position_side: &str,
     quantity: &str,
-    key: &str,
-    secret: &str,
 ) -> Result<BiannceOrder> {
-    let order_response = create_biance_order(
-        symbol,
-        side,
-        position_side,
-        "MARKET", // 假设使用市价单
-        quantity, // 将数量格式化为字符串
-        None,     // 市价单无需价格
-        None,     // 此示例未设置止损价格
-        key,
-        secret,
-    )
-    .await;
+    let order_response = exchange
+        .create_order(OrderRequest::market(symbol, side, position_side, quantity))
+        .await;
     match order_response {
-        Ok(order) => get_biance_active_order(symbol, order.order_id, key, secret).await,
+        Ok(order) => exchange.get_order(symbol, order.order_id).await,
         Err(e) => Err(Error::ErrorMessage(format!("Order failed: {}", e))),
     }
 }
 
+// 挂出止损市价单。双向持仓模式下带 positionSide 的反向单只能减仓，
+// 币安不允许同时发送 reduceOnly 参数
+pub async fn create_stop_order(
+    exchange: &dyn Exchange,
+    symbol: &str,
+    side: &str,
+    position_side: &str,
+    quantity: &str,
+    stop_price: &str,
+) -> Result<ActiveOrder> {
+    exchange
+        .create_order(OrderRequest::stop_market(
+            symbol,
+            side,
+            position_side,
+            quantity,
+            stop_price,
+        ))
+        .await
+        .map_err(|e| Error::ErrorMessage(format!("Stop order failed: {}", e)))
+}
+
+pub async fn cancel_stop_order(exchange: &dyn Exchange, symbol: &str, order_id: u64) -> Result<()> {
+    exchange
+        .cancel_order(symbol, order_id)
+        .await
+        .map_err(|e| Error::ErrorMessage(format!("Cancel stop order failed: {}", e)))?;
+    Ok(())
+}
+
 pub async fn get_symbol_direction_quantity(
+    exchange: &dyn Exchange,
     symbol: &str,
     position_side: &str,
-    key: &str,
-    secret: &str,
 ) -> Result<String> {
-    let risks = get_biance_risk(key, secret)
+    let risks = exchange
+        .get_risk()
         .await
         .map_err(|_e| Error::ErrorMessage("Symbol not found".to_string()))?;
 
@@ -78,18 +108,31 @@ pub async fn get_symbol_direction_quantity(
 }
 
 pub async fn close_position_order(
+    exchange: &dyn Exchange,
     user_id: &str,
     symbol: &str,
     side: &str,
     position_side: &str,
-    key: &str,
-    secret: &str,
 ) -> Result<Vec<TradeRecord>> {
-    let quantity = get_symbol_direction_quantity(symbol, position_side, key, secret).await?;
+    let quantity = get_symbol_direction_quantity(exchange, symbol, position_side).await?;
+    close_position_quantity(exchange, user_id, symbol, side, position_side, &quantity).await
+}
+
+// 市价平掉指定数量并记录手续费
+pub async fn close_position_quantity(
+    exchange: &dyn Exchange,
+    user_id: &str,
+    symbol: &str,
+    side: &str,
+    position_side: &str,
+    quantity: &str,
+) -> Result<Vec<TradeRecord>> {
     let order_response =
-        create_position_order(symbol, side, position_side, &quantity, key, secret).await;
+        create_position_order(exchange, symbol, side, position_side, quantity).await;
     match order_response {
-        Ok(order) => match get_biance_finished_order(symbol, order.order_id, key, secret).await {
+        Ok(order) => match exchange.get_trades(symbol, order.order_id).await {
+            // 模拟成交不计入代理手续费
+            Ok(active_order) if !exchange.records_fees() => Ok(active_order),
             Ok(active_order) => {
                 // 计算佣金
                 let commission = calculate_total_commission(&active_order);
@@ -127,9 +170,9 @@ pub fn trim_trailing_zeros(input: &str) -> String {
 }
 
 pub fn format_url(symbol: &str) -> String {
-    // format!("wss://stream.binance.com:443/ws/{}@miniTicker", symbol)
-    // format!("wss://stream.binance.com:443/ws/{}@trade", symbol)
-    format!("wss://stream.binance.com:443/ws/{}@bookTicker", symbol)
+    // format!("{}/{}@miniTicker", exchange_config().ws_url(), symbol)
+    // format!("{}/{}@trade", exchange_config().ws_url(), symbol)
+    format!("{}/{}@bookTicker", exchange_config().ws_url(), symbol)
 }
 
 #[derive(Deserialize, Debug, Clone)]